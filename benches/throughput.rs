@@ -0,0 +1,200 @@
+//! Throughput of the simulation's hot paths: whole updates of every scene at several swarm sizes,
+//! message delivery, and each obstacle's ray intersection.
+//!
+//! `cargo bench` runs everything; `cargo bench -- columns` only what has `columns` in its name.
+
+use std::{
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use sales_ants::{
+    agent::Message,
+    math::Vec2,
+    sim::obstacle::{Circle, InvRect, Obstacle, Polygon, Rect, Triangle},
+    start,
+};
+
+const SEED: u64 = 1;
+const TICK: f32 = 0.05;
+/// Swarm sizes each scene is updated at; the default swarm is the largest.
+const AGENTS: [usize; 4] = [250, 500, 1000, 2000];
+/// Simulated seconds before timing starts, so the swarm has left its corner.
+const WARM_UP: f32 = 5.0;
+/// Wall-clock time each measurement runs for.
+const BUDGET: Duration = Duration::from_secs(1);
+/// Rays each obstacle is intersected with per round.
+const RAYS: usize = 1024;
+
+/// Calls `round`, which does `per_round` of something, until `BUDGET` is spent. -> things per
+/// second
+fn rate(per_round: usize, mut round: impl FnMut()) -> f64 {
+    let start = Instant::now();
+    let mut rounds = 0;
+    while start.elapsed() < BUDGET {
+        round();
+        rounds += 1;
+    }
+    (rounds * per_round) as f64 / start.elapsed().as_secs_f64()
+}
+
+/// Every scene in `scenes/`, by name.
+fn scenes() -> Vec<(String, String)> {
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/scenes");
+    let mut scenes: Vec<_> = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| {
+            let path = entry.unwrap().path();
+            let name = path.file_stem().unwrap().to_str().unwrap().to_owned();
+            (name, std::fs::read_to_string(&path).unwrap())
+        })
+        .collect();
+    scenes.sort();
+    scenes
+}
+
+/// `World::update` on every scene, per tick and per agent moved.
+fn update(selected: &impl Fn(&str) -> bool) {
+    for (scene_name, scene) in scenes() {
+        for n in AGENTS {
+            let name = format!("update/{scene_name}/{n}");
+            if !selected(&name) {
+                continue;
+            }
+            let mut world = start(&scene, Some(SEED));
+            assert!(
+                n <= world.agents.len(),
+                "{name}: the scene starts with only {} agents",
+                world.agents.len(),
+            );
+            world.agents.truncate(n);
+            while world.time < WARM_UP {
+                world.update(TICK);
+            }
+            // Populations that spawn or retire agents have moved on from `n` by now
+            let agents = world.agents.len();
+            let ticks = rate(1, || world.update(TICK));
+            println!(
+                "{name:<32} {ticks:>12.1} ticks/s {:>14.0} agent·ticks/s",
+                ticks * agents as f64,
+            );
+        }
+    }
+}
+
+/// `World::process_message` on every scene with the default swarm, per message delivered and
+/// per agent it was offered to. Each message is a relay from one of the agents that's no news to
+/// anyone, so this is the cost of finding who hears it.
+fn process_message(selected: &impl Fn(&str) -> bool) {
+    for (scene_name, scene) in scenes() {
+        let name = format!("process_message/{scene_name}");
+        if !selected(&name) {
+            continue;
+        }
+        let mut world = start(&scene, Some(SEED));
+        // Messages come from sites, so there's nothing to deliver without one
+        if world.sites.is_empty() {
+            continue;
+        }
+        while world.time < WARM_UP {
+            world.update(TICK);
+        }
+        let msgs: Vec<Message> = world
+            .agents
+            .iter()
+            .map(|agent| Message {
+                sq_dist: f32::MAX,
+                range: agent.comm,
+                source: agent.pos,
+                hops: 1,
+                ..world.sites[0].collision_msg(0, world.time)
+            })
+            .collect();
+        let n = world.agents.len();
+        let mut next = 0;
+        let delivered = rate(1, || {
+            world.process_message(msgs[next]);
+            next = (next + 1) % msgs.len();
+        });
+        println!(
+            "{name:<32} {delivered:>12.1} msgs/s  {:>14.0} agent·msgs/s",
+            delivered * n as f64,
+        );
+    }
+}
+
+/// `Obstacle::intersects` for every shape, on rays from around it in every direction.
+fn intersects(selected: &impl Fn(&str) -> bool) {
+    let center = Vec2::new(8.0, 5.0);
+    let corner = |angle: f32| center + 2.0 * Vec2::new(angle.cos(), angle.sin());
+    let shapes: [(&str, Box<dyn Obstacle>); 5] = [
+        (
+            "circle",
+            Box::new(Circle {
+                center,
+                radius: 2.0,
+            }),
+        ),
+        (
+            "triangle",
+            Box::new(Triangle {
+                verts: [0.0, 2.1, 4.2].map(corner),
+            }),
+        ),
+        (
+            "polygon",
+            Box::new(Polygon {
+                verts: (0..6).map(|i| corner(i as f32 * 1.05)).collect(),
+            }),
+        ),
+        (
+            "rect",
+            Box::new(Rect {
+                ranges: [6.0..10.0, 3.0..7.0],
+            }),
+        ),
+        (
+            "inv_rect",
+            Box::new(InvRect(Rect {
+                ranges: [0.0..16.0, 0.0..10.0],
+            })),
+        ),
+    ];
+
+    let mut rng = ChaCha8Rng::seed_from_u64(SEED);
+    let rays: Vec<(Vec2, Vec2)> = (0..RAYS)
+        .map(|_| {
+            let origin = Vec2::new(rng.gen_range(0.0..16.0), rng.gen_range(0.0..10.0));
+            let angle: f32 = rng.gen_range(0.0..std::f32::consts::TAU);
+            (origin, Vec2::new(angle.cos(), angle.sin()))
+        })
+        .collect();
+
+    for (shape_name, shape) in shapes {
+        let name = format!("intersects/{shape_name}");
+        if !selected(&name) {
+            continue;
+        }
+        let queries = rate(RAYS, || {
+            for &(origin, ray) in &rays {
+                black_box(shape.intersects(black_box(origin), black_box(ray)));
+            }
+        });
+        println!("{name:<32} {queries:>12.0} rays/s");
+    }
+}
+
+fn main() {
+    // Cargo passes `--bench`; anything else filters by name
+    let filters: Vec<String> = std::env::args()
+        .skip(1)
+        .filter(|arg| !arg.starts_with("--"))
+        .collect();
+    let selected = |name: &str| filters.is_empty() || filters.iter().any(|f| name.contains(f));
+
+    intersects(&selected);
+    process_message(&selected);
+    update(&selected);
+}
@@ -1,8 +1,24 @@
-use crate::{math::Vec2, sim::obstacle::Obstacle};
+use crate::{
+    behaviour::Behaviour,
+    math::Vec2,
+    sim::{
+        field::Field,
+        obstacle::{self, Obstacle},
+        tour::Tour,
+        AcoParams,
+    },
+};
 
-use rand::Rng;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
 use std::f32::consts::PI;
 
+/// Distance short of an obstacle that an agent steers as if it were, so it turns away before
+/// reaching the surface rather than at it.
+const CLEARANCE: f32 = 0.1;
+
+#[derive(Clone, Deserialize, Serialize)]
 pub struct Agent {
     pub pos: Vec2,
     /// radians right-handedly counterclockwise from +x
@@ -18,61 +34,262 @@ pub struct Agent {
     pub is_scout: bool,
     pub shortest_dist: f32,
     pub current_dist: f32,
+    /// shortest completed tour, in tour modes
+    pub best_tour: f32,
+    /// distance travelled since the current tour started
+    pub tour_dist: f32,
+    pub routes: Routes,
+    /// remaining energy; infinite without an energy model
+    pub energy: f32,
+    pub energy_spent: f32,
+    pub failure: Option<Failure>,
+    /// terrain speed multiplier at the agent's position
+    pub speed_scale: f32,
+    /// odometry cost of a unit of distance at the agent's position
+    pub cost_scale: f32,
+    /// flow velocity at the agent's position
+    pub drift: Vec2,
+    /// sites reached so far
+    pub arrivals: u32,
+    /// site the agent is being served at or waiting for
+    pub visit: Option<Visit>,
+    /// other agents' bodies overlapping this one's at the start of the last separation
+    pub contacts: u32,
+    /// source of the agent's random choices, so runs can be repeated and resumed
+    pub rng: ChaCha8Rng,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Failure {
+    /// the agent stops moving and communicating
+    Crash,
+    /// the agent keeps moving but neither sends nor receives messages
+    Radio,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Deserialize, Serialize)]
 pub struct State {
-    /// site kind indexed, (square distance, is targeting site)
-    pub sites: Vec<(f32, bool)>,
+    /// site kind indexed
+    pub sites: Vec<SiteState>,
     /// currently targeted site (kind, square distance)
     pub target: Option<(usize, f32)>,
+    /// kind of the site most recently reached
+    pub last_site: Option<usize>,
+    /// kind of the site the current tour started at
+    pub tour_start: Option<usize>,
+    /// place in `Tour::Ordered` of the site most recently reached, as kinds may repeat in it
+    #[serde(default)]
+    pub tour_step: Option<usize>,
+}
+
+/// What an agent believes about the nearest site of one kind.
+#[derive(Clone, Copy, Deserialize, Serialize)]
+pub struct SiteState {
+    pub sq_dist: f32,
+    /// is targeting site
+    pub targeting: bool,
+    /// hop count of the message the estimate came from
+    pub hops: u32,
+    /// site id the estimate leads to
+    pub origin: Option<usize>,
+    /// creation timestamp of the message the estimate came from
+    pub time: f32,
 }
 
-#[derive(Clone, Copy)]
+impl Default for SiteState {
+    fn default() -> Self {
+        Self {
+            sq_dist: f32::INFINITY,
+            targeting: true,
+            hops: 0,
+            origin: None,
+            time: f32::NEG_INFINITY,
+        }
+    }
+}
+
+/// An agent held at a site with a service time or capacity. Sites are indices into
+/// `World::sites`.
+#[derive(Clone, Copy, Deserialize, Serialize)]
+pub enum Visit {
+    Queued { site: usize, since: f32 },
+    Served { site: usize, until: f32 },
+}
+
+/// Trip lengths between site kinds. Trips that haven't been made are infinite for `best` and NaN
+/// for `latest`.
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct Routes {
+    pub n_kinds: usize,
+    /// indexed `from * n_kinds + to`
+    pub best: Vec<f32>,
+    /// indexed `from * n_kinds + to`
+    pub latest: Vec<f32>,
+    /// completed trips, indexed `from * n_kinds + to`
+    pub trips: Vec<u32>,
+}
+
+impl Routes {
+    pub fn new(n_kinds: usize) -> Self {
+        Self {
+            n_kinds,
+            best: vec![f32::INFINITY; n_kinds * n_kinds],
+            latest: vec![f32::NAN; n_kinds * n_kinds],
+            trips: vec![0; n_kinds * n_kinds],
+        }
+    }
+
+    pub fn record(&mut self, from: usize, to: usize, len: f32) {
+        if from < self.n_kinds && to < self.n_kinds {
+            let idx = from * self.n_kinds + to;
+            self.best[idx] = self.best[idx].min(len);
+            self.latest[idx] = len;
+            self.trips[idx] += 1;
+        }
+    }
+
+    /// -> (best, latest)
+    pub fn get(&self, from: usize, to: usize) -> (f32, f32) {
+        let idx = from * self.n_kinds + to;
+        (self.best[idx], self.latest[idx])
+    }
+}
+
+#[derive(Clone, Copy, Deserialize, Serialize)]
 pub struct Message {
     pub site_kind: usize,
     pub sq_dist: f32,
     pub range: f32,
     pub source: Vec2,
+    /// number of agents that have relayed the message
+    pub hops: u32,
+    /// index into `World::sites` of the site the message started at
+    pub origin: usize,
+    /// world time at which the site emitted the message
+    pub time: f32,
+    /// relay-chain id, if the world tags emissions
+    pub chain: Option<u32>,
+}
+
+/// How agents lose confidence in distance estimates that are not reinforced.
+#[derive(Clone, Copy, Default, Deserialize)]
+pub enum Forgetting {
+    /// estimates only grow with distance travelled
+    #[default]
+    Never,
+    /// estimated distances grow by this fraction per second
+    Decay(f32),
+    /// estimates are reset to infinity this many seconds after they were last reinforced
+    Reset(f32),
+}
+
+impl Forgetting {
+    /// Checks for rates that would shrink estimates or never apply.
+    pub fn check(&self) -> Result<(), String> {
+        match *self {
+            Forgetting::Decay(rate) if rate < 0.0 || rate.is_nan() => Err(format!(
+                "estimates must decay by a share that isn't negative, not {rate}"
+            )),
+            Forgetting::Reset(after) if after < 0.0 || after.is_nan() => Err(format!(
+                "estimates must be reset after a time that isn't negative, not {after}"
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Limits on which messages agents accept and relay.
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(default)]
+pub struct Messaging {
+    /// messages that have been relayed this many times are not relayed further
+    pub max_hops: Option<u32>,
+    /// stored estimates older than this many seconds are replaced by any message
+    pub max_age: Option<f32>,
+    /// tag each site emission with a fresh relay-chain id
+    pub chains: bool,
 }
 
 impl Agent {
+    /// An agent with randomized speed and role and no knowledge of any site; see
+    /// `World::setup_agent`.
+    pub fn random(pos: Vec2, rng: &mut impl Rng) -> Self {
+        Agent {
+            pos,
+            dir: rng.gen_range(0.0..2.0 * PI),
+            state: State {
+                sites: Default::default(),
+                target: None,
+                last_site: None,
+                tour_start: None,
+                tour_step: None,
+            },
+            speed: rng.gen_range(0.1..=1.5),
+            // speed: rng.gen_range(3.5..=4.5),
+            turn: 100.0f32.to_radians(),
+            comm: 0.8,
+            // comm: 2.0,
+            obs_dist: 0.4,
+            is_scout: rng.gen_bool(0.5),
+            shortest_dist: f32::MAX,
+            current_dist: f32::NAN,
+            best_tour: f32::MAX,
+            tour_dist: f32::NAN,
+            routes: Default::default(),
+            energy: f32::INFINITY,
+            energy_spent: 0.0,
+            failure: None,
+            speed_scale: 1.0,
+            cost_scale: 1.0,
+            drift: Vec2::new(0.0, 0.0),
+            arrivals: 0,
+            visit: None,
+            contacts: 0,
+            rng: ChaCha8Rng::seed_from_u64(rng.gen()),
+        }
+    }
+
     pub fn step<'a>(
         &mut self,
         delta: f32,
         obstacles: impl Iterator<Item = &'a dyn Obstacle> + Clone,
+        behaviour: &dyn Behaviour,
     ) {
-        // let speed = if !self.is_scout && self.state.target.is_none() {
-        //     self.speed * 0.2
-        // } else {
-        //     self.speed
-        // };
-        // let speed = self.speed;
-        let speed = if self.is_scout {
-            self.speed * 1.5
-        } else {
-            self.speed
-        };
+        let speed = behaviour.speed(self) * self.speed_scale;
 
         // self.pos += self.speed * delta * Vec2::new(self.dir.cos(), self.dir.sin());
         let mut origin = self.pos;
         let dist = speed * delta;
-        self.current_dist += dist;
-        let mut pos_delta = dist * Vec2::new(self.dir.cos(), self.dir.sin());
+        let cost = dist * self.cost_scale;
+        self.current_dist += cost;
+        self.tour_dist += cost;
+        let mut pos_delta = dist * Vec2::new(self.dir.cos(), self.dir.sin()) + delta * self.drift;
         let mut collision_limit = 0..10;
         while let Some((hit_pos, refl_delta)) = collision_limit.next().and_then(|_| {
+            // The first obstacle in the way is the one with the nearest hit
             obstacles
                 .clone()
-                .find_map(|obs| obs.process_collision(origin, pos_delta))
+                .filter_map(|obs| obs.process_collision(origin, pos_delta))
+                .min_by(|(a, _), (b, _)| (*a - origin).sq_mag().total_cmp(&(*b - origin).sq_mag()))
         }) {
             origin = hit_pos;
             pos_delta = refl_delta;
+            // Drift carries the agent without turning it, so only bounces change the heading
+            self.dir = pos_delta.angle();
+        }
+        // Out of bounces, wedged where obstacles meet: stay put rather than go through one
+        if !obstacle::blocked(obstacles, origin + pos_delta) {
+            self.pos = origin + pos_delta;
         }
-        self.pos = origin + pos_delta;
-        self.dir = pos_delta.angle();
 
-        for (kind, sq_dist) in self.state.sites.iter_mut().map(|(d, _)| d).enumerate() {
-            *sq_dist = (sq_dist.sqrt() + speed * delta).powi(2);
+        for (kind, sq_dist) in self
+            .state
+            .sites
+            .iter_mut()
+            .map(|st| &mut st.sq_dist)
+            .enumerate()
+        {
+            *sq_dist = (sq_dist.sqrt() + cost).powi(2);
             if let Some(t_dist) = self
                 .state
                 .target
@@ -82,77 +299,90 @@ impl Agent {
                 *t_dist = *sq_dist;
             }
         }
+    }
 
-        self.dir += rand::thread_rng().gen_range(-delta * self.turn..delta * self.turn);
-        self.dir = self.dir.rem_euclid(2.0 * PI);
+    /// Uses up to `amount` of the remaining energy.
+    pub fn spend(&mut self, amount: f32) {
+        let used = amount.min(self.energy.max(0.0));
+        self.energy -= used;
+        self.energy_spent += used;
+    }
+
+    pub fn forget(&mut self, forgetting: Forgetting, now: f32, delta: f32) {
+        if let Forgetting::Never = forgetting {
+            return;
+        }
+        let mut forgot_target = false;
+        for (kind, state) in self.state.sites.iter_mut().enumerate() {
+            match forgetting {
+                Forgetting::Never => {}
+                Forgetting::Decay(rate) => state.sq_dist *= (2.0 * rate * delta).exp(),
+                Forgetting::Reset(after) => {
+                    if now - state.time > after {
+                        state.sq_dist = f32::INFINITY;
+                    }
+                }
+            }
+            // ACO targets carry no estimate to forget
+            if let Some((_, t_dist)) = self
+                .state
+                .target
+                .as_mut()
+                .filter(|(t_kind, t_dist)| *t_kind == kind && t_dist.is_finite())
+            {
+                *t_dist = state.sq_dist;
+                forgot_target = t_dist.is_infinite();
+            }
+        }
+        if forgot_target {
+            self.state.target = None;
+        }
     }
 
-    pub fn inform(&mut self, msg: Message) -> Option<Message> {
+    pub fn inform(
+        &mut self,
+        msg: Message,
+        now: f32,
+        limits: Messaging,
+        tour: &Tour,
+        behaviour: &dyn Behaviour,
+    ) -> Option<Message> {
         // msg.sq_dist = msg.sq (msg.source - self.pos).mag();
         // msg.sq_dist = (msg.sq_dist.sqrt() - msg.range + (msg.source - self.pos).mag())
         //     .powi(2)
         //     .min(msg.sq_dist);
-        let state = self
-            .state
-            .sites
-            .get_mut(msg.site_kind)
-            .filter(|st| msg.sq_dist < st.0)?;
+        let state = self.state.sites.get_mut(msg.site_kind).filter(|st| {
+            msg.sq_dist < st.sq_dist
+                || limits
+                    .max_age
+                    .is_some_and(|max_age| now - st.time > max_age)
+        })?;
         // else {
         //     return Vec::new();
         // }
 
-        state.0 = msg.sq_dist;
-
-        if state.1 && !self.is_scout {
-            if self
-                .state
-                .target
-                .map(|site| msg.sq_dist < site.1)
-                .unwrap_or(true)
-            {
-                self.state.target = Some((msg.site_kind, state.0));
-                let diff = msg.source - self.pos;
-                self.dir = diff.y.atan2(diff.x);
-            }
-            // 180 away from messages about non targeted sites
-            // else {
-            //     let diff = self.pos - msg.source;
-            //     self.dir = diff.y.atan2(diff.x);
-            // }
+        state.sq_dist = msg.sq_dist;
+        state.hops = msg.hops;
+        state.origin = Some(msg.origin);
+        state.time = msg.time;
 
+        if state.targeting && !self.is_scout {
+            behaviour.on_message(self, msg);
             if msg.sq_dist == 0.0 {
-                // self.dir = rand::thread_rng().gen_range(0.0..2.0 * PI);
-                self.dir = (self.dir + PI).rem_euclid(2.0 * PI);
-                state.1 = false;
-
-                if self.state.sites.iter().all(|site| !site.1) {
-                    self.state
-                        .sites
-                        .iter_mut()
-                        .enumerate()
-                        .for_each(|(i, site)| site.1 = i != msg.site_kind);
-                }
-
-                if self.current_dist.is_nan() {
-                    self.current_dist = 0.0;
-                } else if self
-                    .state
-                    .target
-                    .map(|v| v.0 == msg.site_kind)
-                    .unwrap_or(false)
-                {
-                    self.shortest_dist = self.shortest_dist.min(self.current_dist);
-                    self.current_dist = 0.0;
-                }
-
-                self.state.target = None;
+                self.arrive(msg.site_kind, tour);
+                behaviour.on_arrival(self, msg.site_kind);
             }
         }
 
+        if limits.max_hops.is_some_and(|max_hops| msg.hops >= max_hops) {
+            return None;
+        }
+
         Some(Message {
-            sq_dist: (msg.sq_dist.sqrt() + self.comm).powi(2),
+            sq_dist: (msg.sq_dist.sqrt() + self.comm * self.cost_scale).powi(2),
             range: self.comm,
             source: self.pos,
+            hops: msg.hops + 1,
             ..msg
         })
         // vec![
@@ -177,9 +407,158 @@ impl Agent {
         // ]
     }
 
+    /// Whether a site contact message would make the agent arrive.
+    pub fn arriving(&self, msg: Message) -> bool {
+        !self.is_scout
+            && self
+                .state
+                .sites
+                .get(msg.site_kind)
+                .is_some_and(|st| st.targeting && msg.sq_dist < st.sq_dist)
+    }
+
+    /// Targets the site kind a message is about if it is closer than the current target.
+    /// Returns whether the target changed.
+    pub fn retarget(&mut self, msg: Message) -> bool {
+        if self
+            .state
+            .target
+            .map(|site| msg.sq_dist < site.1)
+            .unwrap_or(true)
+        {
+            self.state.target = Some((msg.site_kind, msg.sq_dist));
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Trip and tour bookkeeping for reaching a targeted site of `kind`.
+    fn arrive(&mut self, kind: usize, tour: &Tour) {
+        self.arrivals += 1;
+        self.state.sites[kind].targeting = false;
+
+        let touring = self.state.tour_start.is_some();
+        if tour.arrive(kind, &mut self.state) {
+            self.best_tour = self.best_tour.min(self.tour_dist);
+            self.tour_dist = 0.0;
+        } else if !touring && self.state.tour_start.is_some() {
+            self.tour_dist = 0.0;
+        }
+
+        if let Some(from) = self
+            .state
+            .last_site
+            .filter(|_| self.current_dist.is_finite())
+        {
+            self.routes.record(from, kind, self.current_dist);
+        }
+
+        if self.current_dist.is_nan() {
+            self.current_dist = 0.0;
+        } else if self.state.target.map(|v| v.0 == kind).unwrap_or(false) {
+            self.shortest_dist = self.shortest_dist.min(self.current_dist);
+            self.current_dist = 0.0;
+        }
+
+        self.state.target = None;
+        self.state.last_site = Some(kind);
+    }
+
+    /// Pheromone counterpart of `inform`: reads the distance tags under the agent, heads up the
+    /// gradient of a targeted kind whose estimate improved and marks the field with its own
+    /// estimates.
+    pub fn sense(&mut self, field: &mut Field, scale: f32, now: f32) {
+        let mut best: Option<(usize, f32)> = None;
+        for (kind, state) in self.state.sites.iter_mut().enumerate() {
+            let marker = field.sample(kind, self.pos);
+            if marker > 0.0 {
+                // Markers can't place a site closer than the cell they are read from; only site
+                // contact gives an exact zero.
+                let dist = (-scale * marker.ln()).max(field.cell);
+                let sq_dist = dist.powi(2);
+                // Ignore gains smaller than a cell, which are mostly the agent's own marker
+                if (dist + field.cell).powi(2) < state.sq_dist {
+                    state.sq_dist = sq_dist;
+                    state.hops = 0;
+                    state.origin = None;
+                    state.time = now;
+                    if state.targeting && best.map(|(_, d)| sq_dist < d).unwrap_or(true) {
+                        best = Some((kind, sq_dist));
+                    }
+                }
+            }
+            if state.sq_dist.is_finite() {
+                field.deposit(kind, self.pos, (-state.sq_dist.sqrt() / scale).exp());
+            }
+        }
+
+        let Some((kind, sq_dist)) = best.filter(|_| !self.is_scout) else {
+            return;
+        };
+        if self
+            .state
+            .target
+            .map(|site| sq_dist < site.1)
+            .unwrap_or(true)
+        {
+            self.state.target = Some((kind, sq_dist));
+            let grad = field.gradient(kind, self.pos);
+            if grad.sq_mag() > 0.0 {
+                self.dir = grad.angle().rem_euclid(2.0 * PI);
+            }
+        }
+    }
+
+    /// Ant colony optimization counterpart of `inform`: lays pheromone for the site the agent last
+    /// reached and turns towards the targeted kinds' pheromone ahead with probability
+    /// proportional to its concentration raised to `alpha`.
+    pub fn forage(&mut self, field: &mut Field, params: AcoParams, speed: f32, delta: f32) {
+        if let Some(kind) = self.state.last_site {
+            let fade = (-self.current_dist.max(0.0) / params.fade).exp();
+            field.add(kind, self.pos, params.deposit * speed * delta * fade);
+        }
+
+        if self.is_scout {
+            return;
+        }
+        let max_turn = params.sense_angle.min(params.steer * delta);
+        let mut strongest: Option<(usize, f32)> = None;
+        let weights = [-1.0, 0.0, 1.0].map(|side| {
+            let dir = self.dir + side * params.sense_angle;
+            let probe = self.pos + params.sense_dist * Vec2::new(dir.cos(), dir.sin());
+            let mut concentration = 0.0;
+            for (kind, _) in self
+                .state
+                .sites
+                .iter()
+                .enumerate()
+                .filter(|(_, st)| st.targeting)
+            {
+                let sample = field.sample(kind, probe);
+                concentration += sample;
+                if sample > strongest.map(|(_, v)| v).unwrap_or(0.0) {
+                    strongest = Some((kind, sample));
+                }
+            }
+            (concentration + 1e-3).powf(params.alpha)
+        });
+        // The estimate is unknown, so any site contact message will be better
+        self.state.target = strongest.map(|(kind, _)| (kind, f32::INFINITY));
+        let mut pick = self.rng.gen_range(0.0..weights.iter().sum::<f32>());
+        let side = weights
+            .iter()
+            .position(|&w| {
+                pick -= w;
+                pick < 0.0
+            })
+            .unwrap_or(1);
+        self.dir = (self.dir + (side as f32 - 1.0) * max_turn).rem_euclid(2.0 * PI);
+    }
+
     pub fn contain(&mut self, world_size: Vec2) {
         use std::f32::consts::FRAC_PI_2;
-        [0, 1].map(|i| {
+        for i in [0, 1] {
             // <0: outside negative
             //  0: inside
             // >0: outside positive
@@ -193,19 +572,19 @@ impl Agent {
             if pos_status * dir_status > 0 {
                 self.dir = ((1 - i) as f32 * PI - self.dir).rem_euclid(2.0 * PI);
             }
-        });
+        }
     }
 
     pub fn avoid_obstacles<'a>(&mut self, obstacles: impl Iterator<Item = &'a dyn Obstacle>) {
         let ray = Vec2::new(self.dir.cos(), self.dir.sin());
-        let Some((t, norm)) = obstacles
-            .flat_map(|o| o.intersects(self.pos, ray))
-            .filter(|&(t, _)| t > 0.0 && t < self.obs_dist)
-            .min_by(|a, b| a.0.total_cmp(&b.0))
+        let Some(hit) = obstacles
+            .filter_map(|o| o.nearest_hit(self.pos, ray, self.obs_dist))
+            .min_by(|a, b| a.t().total_cmp(&b.t()))
         else {
             return;
         };
-        let t = t - 0.1;
+        // `ray` is a unit vector, so `t` is a distance
+        let (t, norm) = (hit.t() - CLEARANCE, hit.normal());
 
         use std::f32::consts::FRAC_PI_2;
 
@@ -227,7 +606,13 @@ impl crate::sim::render::Renderable for Agent {
 
         let trails = crate::var("TRAILS");
 
-        let color = if self.is_scout {
+        let color = if self.failure == Some(Failure::Crash) {
+            if trails {
+                [0x08, 0x00, 0x00]
+            } else {
+                [0x60, 0x20, 0x20]
+            }
+        } else if self.is_scout {
             // [0x4e; 3]
             if trails {
                 [0x10; 3]
@@ -0,0 +1,152 @@
+use serde::Deserialize;
+use std::f32::consts::PI;
+
+use crate::{
+    agent::{Agent, Message},
+    math::Vec2,
+    sim::obstacle::Obstacle,
+};
+
+use rand::Rng;
+
+/// Movement and decision rules of agents. Message filtering, relaying and trip bookkeeping stay
+/// in `Agent`; behaviours decide how fast agents go, where they head and what they do about
+/// what they hear.
+pub trait Behaviour {
+    /// Distance per second covered this tick.
+    fn speed(&self, agent: &Agent) -> f32 {
+        agent.speed
+    }
+
+    /// Heading changes after moving.
+    fn steer(
+        &self,
+        agent: &mut Agent,
+        delta: f32,
+        world_size: Vec2,
+        obstacles: &[Box<dyn Obstacle>],
+    ) {
+        let max_turn = delta * agent.turn;
+        agent.dir += agent.rng.gen_range(-max_turn..max_turn);
+        agent.dir = agent.dir.rem_euclid(2.0 * PI);
+        agent.contain(world_size);
+        agent.avoid_obstacles(obstacles.iter().map(Box::as_ref));
+    }
+
+    /// A non-scout accepted a message about a site kind it is targeting.
+    fn on_message(&self, agent: &mut Agent, msg: Message);
+
+    /// A non-scout reached a site of a kind it was targeting.
+    fn on_arrival(&self, agent: &mut Agent, _kind: usize) {
+        agent.dir = (agent.dir + PI).rem_euclid(2.0 * PI);
+    }
+}
+
+/// The distributed shortest path algorithm: head towards whoever relayed the best estimate of a
+/// targeted site.
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct Dijkstra {
+    /// speed multiplier for scouts
+    pub scout_speed: f32,
+    /// speed multiplier for workers without a target
+    pub idle_speed: f32,
+    /// turn around on arrival rather than leaving in a random direction
+    pub reverse_on_arrival: bool,
+}
+
+impl Default for Dijkstra {
+    fn default() -> Self {
+        Self {
+            scout_speed: 1.5,
+            idle_speed: 1.0,
+            reverse_on_arrival: true,
+        }
+    }
+}
+
+impl Behaviour for Dijkstra {
+    fn speed(&self, agent: &Agent) -> f32 {
+        if agent.is_scout {
+            agent.speed * self.scout_speed
+        } else if agent.state.target.is_none() {
+            agent.speed * self.idle_speed
+        } else {
+            agent.speed
+        }
+    }
+
+    fn on_message(&self, agent: &mut Agent, msg: Message) {
+        if agent.retarget(msg) {
+            let diff = msg.source - agent.pos;
+            agent.dir = diff.y.atan2(diff.x);
+        }
+        // 180 away from messages about non targeted sites
+        // else {
+        //     let diff = agent.pos - msg.source;
+        //     agent.dir = diff.y.atan2(diff.x);
+        // }
+    }
+
+    fn on_arrival(&self, agent: &mut Agent, _kind: usize) {
+        agent.dir = if self.reverse_on_arrival {
+            (agent.dir + PI).rem_euclid(2.0 * PI)
+        } else {
+            agent.rng.gen_range(0.0..2.0 * PI)
+        };
+    }
+}
+
+/// Random walk that keeps track of targets but never steers towards them; a null baseline.
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct Wander {
+    /// speed multiplier for scouts
+    pub scout_speed: f32,
+}
+
+impl Default for Wander {
+    fn default() -> Self {
+        Self { scout_speed: 1.5 }
+    }
+}
+
+impl Behaviour for Wander {
+    fn speed(&self, agent: &Agent) -> f32 {
+        if agent.is_scout {
+            agent.speed * self.scout_speed
+        } else {
+            agent.speed
+        }
+    }
+
+    fn on_message(&self, agent: &mut Agent, msg: Message) {
+        agent.retarget(msg);
+    }
+}
+
+pub fn default_behaviour() -> Box<dyn Behaviour> {
+    Box::<Dijkstra>::default()
+}
+
+pub fn deser_behaviour<'de, D>(d: D) -> Result<Box<dyn Behaviour>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    macro_rules! gen_behaviour_deser {
+        ($($behaviour:ident),*) => {
+            #[derive(Deserialize)]
+            enum BehaviourObj { $($behaviour($behaviour)),* }
+
+            let obj: BehaviourObj = Deserialize::deserialize(d)?;
+
+            return Ok(match obj {
+                $(
+                    BehaviourObj::$behaviour(v) => Box::new(v) as Box<dyn Behaviour>,
+                )*
+            });
+        };
+    }
+
+    gen_behaviour_deser!(Dijkstra, Wander);
+}
@@ -0,0 +1,133 @@
+use sales_ants::metrics::Format;
+
+const USAGE: &str = "\
+usage: sales-ants [SCENE] [OPTIONS]
+       sales-ants --replay FILE
+
+  --hide-cursor          hide the mouse cursor over the window
+  --csv FILE             log metrics as CSV
+  --jsonl FILE           log metrics as JSON Lines
+  --interval SECONDS     simulated time between metrics samples [1]
+  --record FILE          record agent trajectories every tick
+  --record-fps FPS       record at most FPS frames per simulated second instead
+  --headless             simulate without a window
+  --duration SECONDS     simulated time to run headless [600]
+  --tick SECONDS         simulated time per headless update [1/60]
+  --replay FILE          play back a recording
+  --checkpoint FILE      save the simulation state periodically and at the end of a headless run
+  --checkpoint-every SECONDS
+                         simulated time between checkpoints [60]
+  --resume FILE          carry on from a checkpoint instead of loading a scene
+  --heatmap PREFIX       map where workers travel and export it with the routes they favour
+                         as PREFIX.ppm and PREFIX.csv, periodically and at the end of a headless
+                         run
+  --heatmap-every SECONDS
+                         simulated time between heatmap exports [60]
+  --seed SEED            seed the random number generators for a reproducible run
+  --seeds N              run headless once for each of N seeds from --seed [0] and summarise
+                         how quickly and how closely the swarm finds the shortest route
+  --within PERCENT       tolerance on the shortest route summarised by --seeds [5]";
+
+/// Command line options.
+pub struct Args {
+    pub scene: String,
+    pub hide_cursor: bool,
+    /// where to log metrics, and how
+    pub metrics: Option<(String, Format)>,
+    /// seconds of simulated time between metrics samples
+    pub interval: f32,
+    pub record: Option<String>,
+    /// most recorded frames per simulated second; every tick without it
+    pub record_fps: Option<f32>,
+    pub headless: bool,
+    pub duration: f32,
+    pub tick: f32,
+    pub replay: Option<String>,
+    pub checkpoint: Option<String>,
+    pub checkpoint_every: f32,
+    pub resume: Option<String>,
+    pub heatmap: Option<String>,
+    pub heatmap_every: f32,
+    pub seed: Option<u64>,
+    pub seeds: Option<u64>,
+    /// fraction above the optimum counted as having found the shortest route
+    pub within: f32,
+}
+
+impl Args {
+    pub fn parse() -> Self {
+        let mut args = Self {
+            scene: "scenes/default.ron".to_string(),
+            hide_cursor: false,
+            metrics: None,
+            interval: 1.0,
+            record: None,
+            record_fps: None,
+            headless: false,
+            duration: 600.0,
+            tick: 1.0 / 60.0,
+            replay: None,
+            checkpoint: None,
+            checkpoint_every: 60.0,
+            resume: None,
+            heatmap: None,
+            heatmap_every: 60.0,
+            seed: None,
+            seeds: None,
+            within: 0.05,
+        };
+
+        let mut iter = std::env::args().skip(1);
+        while let Some(arg) = iter.next() {
+            let mut value = || {
+                iter.next()
+                    .unwrap_or_else(|| usage(&format!("{arg} needs a value")))
+            };
+            let number = |v: String| -> f32 {
+                v.parse()
+                    .ok()
+                    .filter(|v: &f32| *v > 0.0)
+                    .unwrap_or_else(|| usage(&format!("{arg} needs a positive number")))
+            };
+            let count = |v: String| -> u64 {
+                v.parse()
+                    .unwrap_or_else(|_| usage(&format!("{arg} needs a whole number")))
+            };
+            match arg.as_str() {
+                "--hide-cursor" => args.hide_cursor = true,
+                "--csv" => args.metrics = Some((value(), Format::Csv)),
+                "--jsonl" => args.metrics = Some((value(), Format::Jsonl)),
+                "--interval" => args.interval = number(value()),
+                "--record" => args.record = Some(value()),
+                "--record-fps" => args.record_fps = Some(number(value())),
+                "--headless" => args.headless = true,
+                "--duration" => args.duration = number(value()),
+                "--tick" => args.tick = number(value()),
+                "--replay" => args.replay = Some(value()),
+                "--checkpoint" => args.checkpoint = Some(value()),
+                "--checkpoint-every" => args.checkpoint_every = number(value()),
+                "--resume" => args.resume = Some(value()),
+                "--heatmap" => args.heatmap = Some(value()),
+                "--heatmap-every" => args.heatmap_every = number(value()),
+                "--seed" => args.seed = Some(count(value())),
+                "--seeds" => match count(value()) {
+                    0 => usage("--seeds needs at least one seed"),
+                    n => args.seeds = Some(n),
+                },
+                "--within" => args.within = number(value()) / 100.0,
+                "-h" | "--help" => usage(""),
+                flag if flag.starts_with('-') => usage(&format!("unknown option {flag}")),
+                _ => args.scene = arg,
+            }
+        }
+        args
+    }
+}
+
+fn usage(problem: &str) -> ! {
+    if !problem.is_empty() {
+        eprintln!("{problem}");
+    }
+    eprintln!("{USAGE}");
+    std::process::exit(2);
+}
@@ -0,0 +1,56 @@
+pub mod agent;
+pub mod behaviour;
+pub mod math;
+pub mod metrics;
+pub mod record;
+pub mod report;
+pub mod sim;
+pub mod stats;
+
+use rand::SeedableRng;
+
+/// Parses a scene and walls it in.
+pub fn load_scene(scene: &str) -> sim::World {
+    let mut world: sim::World = ron::from_str(scene).unwrap();
+    if let Err(e) = world.check() {
+        panic!("invalid scene: {e}");
+    }
+    world
+        .obstacles
+        .push(Box::new(sim::obstacle::InvRect(sim::obstacle::Rect {
+            ranges: [0.0..world.world_size.x, 0.0..world.world_size.y],
+        })));
+    world
+}
+
+/// Loads a scene and releases the swarm into it, seeding the world's random numbers with `seed`
+/// if given.
+pub fn start(scene: &str, seed: Option<u64>) -> sim::World {
+    let mut world = load_scene(scene);
+    if let Some(seed) = seed {
+        world.rng = rand_chacha::ChaCha8Rng::seed_from_u64(seed);
+    }
+    world.agents = sim::World::random_agents(&mut world.rng);
+    for agent in &mut world.agents {
+        if sim::obstacle::blocked(world.obstacles.iter().map(Box::as_ref), agent.pos) {
+            agent.pos = world.world_size / 2.0;
+        }
+    }
+
+    let mut agents = std::mem::take(&mut world.agents);
+    for agent in &mut agents {
+        world.setup_agent(agent);
+    }
+    world.agents = agents;
+    world
+}
+
+pub fn var<T: std::str::FromStr + Default>(name: &'static str) -> T {
+    // T::from_str(&std::env::var(name).unwrap())
+    //     .ok()
+    //     .unwrap_or_default()
+    std::env::var(name)
+        .ok()
+        .and_then(|v| T::from_str(&v).ok())
+        .unwrap_or_default()
+}
@@ -1,91 +1,133 @@
-mod agent;
-mod math;
-mod sim;
+mod cli;
 
-use pixels::{PixelsBuilder, SurfaceTexture};
+use pixels::{Pixels, PixelsBuilder, SurfaceTexture};
 use rand::Rng;
-use std::time::Instant;
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
 use winit::{
     dpi::LogicalSize,
+    event::{ElementState, Event, KeyboardInput, VirtualKeyCode, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
-    window::WindowBuilder,
+    window::{Window, WindowBuilder},
 };
 
-use crate::sim::render::Renderable;
+use sales_ants::{
+    load_scene, math::Vec2, metrics, record, report, sim, sim::render::Renderable, start, stats,
+    var,
+};
 
+/// size the window opens at
 const SCREEN_DIMS: (u32, u32) = (1440, 900);
+const BACKGROUND: [u8; 3] = [0x1e, 0x1f, 0x2e];
+const FRAME_TIME_MIN: Duration = Duration::from_millis(16);
+/// seconds of recording skipped by the arrow keys
+const REPLAY_SEEK: f32 = 5.0;
 
 fn main() {
     let mut rng = rand::thread_rng();
-
-    let world_f = std::env::args().nth(1);
-    let world_f = world_f.as_deref().unwrap_or("scenes/default.ron");
-    let world_s = std::fs::read_to_string(world_f).unwrap();
-    let mut world: sim::World = ron::from_str(&world_s).unwrap();
-    world
-        .obstacles
-        .push(Box::new(sim::obstacle::InvRect(sim::obstacle::Rect {
-            ranges: [0.0..world.world_size.x, 0.0..world.world_size.y],
-        })));
-    world.agents = sim::World::new().agents;
-    for agent in &mut world.agents {
-        if world
-            .obstacles
-            .iter()
-            .map(|obs| (obs.bounding_box(), obs))
-            .any(|(bbox, obs)| {
-                [0, 1].map(|i| bbox[i].contains(&agent.pos[i])) == [true; 2]
-                    && obs.inside(agent.pos)
-            })
-        {
-            agent.pos = world.world_size / 2.0;
-        }
+    let args = cli::Args::parse();
+    if let Some(path) = &args.replay {
+        replay(path, args.hide_cursor);
+        return;
+    }
+    if let Some(n) = args.seeds {
+        summarise_seeds(&args, n);
+        return;
     }
 
-    let n_sites =
-        world
-            .sites
-            .iter()
-            .map(|site| site.kind + 1)
-            .max()
-            .unwrap_or(0);
-    world.agents.iter_mut().for_each(|agent| {
-        agent.state.sites = vec![(f32::INFINITY, true); n_sites];
-        agent.state.sites[rng.gen_range(0..=1)].1 = false;
-    });
-
-    let event_loop = EventLoop::new();
-    let window = {
-        let size = LogicalSize::new(SCREEN_DIMS.0, SCREEN_DIMS.1);
-        WindowBuilder::new()
-            .with_inner_size(size)
-            .with_decorations(false)
-            .build(&event_loop)
-            .expect("WindowBuilder failed")
+    let (scene, mut world) = if let Some(path) = &args.resume {
+        let checkpoint = sim::checkpoint::Checkpoint::load(path).unwrap_or_else(|e| {
+            eprintln!("{e}");
+            std::process::exit(1);
+        });
+        let scene = checkpoint.scene.clone();
+        let mut world = load_scene(&scene);
+        checkpoint.restore(&mut world);
+        (scene, world)
+    } else {
+        let scene = std::fs::read_to_string(&args.scene).unwrap();
+        let world = start(&scene, args.seed);
+        (scene, world)
     };
-    if let Some("--hide-cursor") = std::env::args().nth(1).as_deref() {
-        window.set_cursor_visible(false);
+    let n_sites = world.n_kinds();
+    if args.heatmap.is_some() && world.heatmap.is_none() {
+        world.heatmap = Some(sim::heatmap::new(&world));
     }
 
-    let mut pixels = {
-        let window_size = window.inner_size();
-        let surface_texture = SurfaceTexture::new(window_size.width, window_size.height, &window);
-        PixelsBuilder::new(SCREEN_DIMS.0, SCREEN_DIMS.1, surface_texture)
-            .build()
-            .expect("PixelsBuilder failed")
+    for (from, dists) in world.kind_distances().iter().enumerate() {
+        for (to, dist) in dists.iter().enumerate().filter(|&(to, _)| to != from) {
+            eprintln!("ground truth\t{from}\t{to}\t{dist}");
+        }
+    }
+    if let Some((len, order)) = world.optimal_tour() {
+        eprintln!("optimal tour\t{len}\t{order:?}");
+    }
+
+    let mut reporter = report::Reporter::new(&world);
+    let mut outputs = Outputs {
+        metrics: args
+            .metrics
+            .as_ref()
+            .map(|(path, format)| metrics::Metrics::create(path, *format, args.interval, n_sites)),
+        recorder: args
+            .record
+            .as_ref()
+            .map(|path| record::Recorder::create(path, &world, &scene, args.record_fps)),
+        checkpoint: args
+            .checkpoint
+            .clone()
+            .map(|path| Periodic::new(path, args.checkpoint_every, world.time)),
+        heatmap: args
+            .heatmap
+            .clone()
+            .map(|prefix| Periodic::new(prefix, args.heatmap_every, world.time)),
+        scene: scene.clone(),
     };
-    pixels.frame_mut().fill(0xff);
 
-    let mut shortest_dist = f32::MAX;
+    if args.headless {
+        while world.time < args.duration {
+            world.update(args.tick);
+            outputs.record(&world);
+            reporter.report(&world, world.time);
+        }
+        outputs.finish(&world);
+        return;
+    }
+
+    let event_loop = EventLoop::new();
+    let (window, mut pixels, mut scale) =
+        open_window(&event_loop, args.hide_cursor, world.world_size);
 
     let trails = var("TRAILS");
 
-    let mut trail_buf = vec![0u8; (SCREEN_DIMS.0 * SCREEN_DIMS.1 * 4) as usize].into_boxed_slice();
+    let mut trail_buf = vec![0u8; pixels.frame().len()].into_boxed_slice();
 
     let start = Instant::now();
     let mut last_loop = Instant::now();
-    const FRAME_TIME_MIN: std::time::Duration = std::time::Duration::from_millis(16);
-    event_loop.run(move |_event, _, control_flow| {
+    event_loop.run(move |event, _, control_flow| {
+        match event {
+            Event::WindowEvent {
+                event: WindowEvent::CloseRequested,
+                ..
+            } => control_flow.set_exit(),
+            Event::WindowEvent {
+                event: WindowEvent::Resized(_) | WindowEvent::ScaleFactorChanged { .. },
+                ..
+            } => {
+                if let Some(fitted) = fit(&mut pixels, world.world_size, &window) {
+                    scale = fitted;
+                    // Trails are in pixels, so they start again at the new scale
+                    trail_buf = vec![0u8; pixels.frame().len()].into_boxed_slice();
+                }
+            }
+            _ => {}
+        }
+
         let now = Instant::now();
         let mut delta = now - last_loop;
         if delta < FRAME_TIME_MIN {
@@ -99,10 +141,11 @@ fn main() {
 
         frame
             .chunks_mut(4)
-            .for_each(|px| px[0..3].copy_from_slice(&[0x1e, 0x1f, 0x2e]));
+            .for_each(|px| px[0..3].copy_from_slice(&BACKGROUND));
 
         world.update(delta);
-        world.render(frame, 90.0, 1440);
+        outputs.record(&world);
+        world.render(frame, scale.px_per_unit, scale.px_width);
 
         // world.agents[0].render(&mut sim::render::RenderArgs {
         //     world: &world,
@@ -119,8 +162,8 @@ fn main() {
                 agent.render(&mut sim::render::RenderArgs {
                     world: &world,
                     frame: &mut trail_buf,
-                    px_per_unit: 90.0,
-                    px_width: 1440,
+                    px_per_unit: scale.px_per_unit,
+                    px_width: scale.px_width,
                 });
             }
             frame
@@ -137,16 +180,7 @@ fn main() {
                 .for_each(|v| *v = v.saturating_sub(rng.gen_bool(0.5) as u8));
         }
 
-        let new_shortest_dist = world
-            .agents
-            .iter()
-            .map(|a| a.shortest_dist)
-            .min_by(|a, b| a.total_cmp(b))
-            .unwrap();
-        if new_shortest_dist < shortest_dist {
-            shortest_dist = new_shortest_dist;
-            println!("{}\t{shortest_dist}", start.elapsed().as_secs_f32());
-        }
+        reporter.report(&world, start.elapsed().as_secs_f32());
 
         if let ControlFlow::ExitWithCode(code) = control_flow {
             std::process::exit(*code);
@@ -154,12 +188,287 @@ fn main() {
     });
 }
 
-fn var<T: std::str::FromStr + Default>(name: &'static str) -> T {
-    // T::from_str(&std::env::var(name).unwrap())
-    //     .ok()
-    //     .unwrap_or_default()
-    std::env::var(name)
-        .ok()
-        .and_then(|v| T::from_str(&v).ok())
-        .unwrap_or_default()
+/// Runs the scene headless for `n` consecutive seeds, one per core at a time, and reports how
+/// the runs compare.
+fn summarise_seeds(args: &cli::Args, n: u64) {
+    let scene = std::fs::read_to_string(&args.scene).unwrap();
+    let Some(optimal) = stats::optimal(&load_scene(&scene)) else {
+        eprintln!(
+            "{} has no route between sites of different kinds",
+            args.scene
+        );
+        std::process::exit(1);
+    };
+    eprintln!("optimal\t{optimal}");
+
+    let first = args.seed.unwrap_or(0);
+    let next = AtomicU64::new(first);
+    let trials = Mutex::new(Vec::new());
+    let workers = std::thread::available_parallelism().map_or(1, |n| n.get());
+    std::thread::scope(|scope| {
+        for _ in 0..workers.min(n as usize) {
+            scope.spawn(|| loop {
+                let seed = next.fetch_add(1, Ordering::Relaxed);
+                if seed >= first + n {
+                    break;
+                }
+                let world = start(&scene, Some(seed));
+                let trial =
+                    stats::Trial::run(world, seed, args.duration, args.tick, optimal, args.within);
+                let within = if trial.time_within.is_nan() {
+                    "never".to_string()
+                } else {
+                    format!("at {} s", trial.time_within)
+                };
+                eprintln!(
+                    "seed {seed}: within {}% {within}, final ratio {}",
+                    args.within * 100.0,
+                    trial.ratio
+                );
+                trials.lock().unwrap().push(trial);
+            });
+        }
+    });
+
+    let mut trials = trials.into_inner().unwrap();
+    trials.sort_by_key(|trial| trial.seed);
+    stats::report(&trials, args.within);
+}
+
+/// Files written as the world runs.
+struct Outputs {
+    metrics: Option<metrics::Metrics>,
+    recorder: Option<record::Recorder>,
+    checkpoint: Option<Periodic>,
+    heatmap: Option<Periodic>,
+    scene: String,
+}
+
+/// A file rewritten every so often in simulated time.
+struct Periodic {
+    path: String,
+    every: f32,
+    next: f32,
+    /// simulated time of the last write
+    last: f32,
+}
+
+impl Periodic {
+    fn new(path: String, every: f32, now: f32) -> Self {
+        Self {
+            path,
+            every,
+            next: now + every,
+            last: f32::NAN,
+        }
+    }
+
+    /// -> the path, if a write is due
+    fn due(&mut self, now: f32, force: bool) -> Option<&str> {
+        if now >= self.next || (force && now != self.last) {
+            self.next = now + self.every;
+            self.last = now;
+            Some(&self.path)
+        } else {
+            None
+        }
+    }
+}
+
+impl Outputs {
+    fn record(&mut self, world: &sim::World) {
+        if let Some(metrics) = &mut self.metrics {
+            metrics.record(world);
+        }
+        if let Some(recorder) = &mut self.recorder {
+            recorder.record(world);
+        }
+        self.save(world, false);
+    }
+
+    /// Brings the periodic files up to date at the end of a run.
+    fn finish(&mut self, world: &sim::World) {
+        self.save(world, true);
+    }
+
+    fn save(&mut self, world: &sim::World, force: bool) {
+        if let Some(path) = self
+            .checkpoint
+            .as_mut()
+            .and_then(|c| c.due(world.time, force))
+        {
+            save_checkpoint(world, &self.scene, path);
+        }
+        if let Some(prefix) = self.heatmap.as_mut().and_then(|h| h.due(world.time, force)) {
+            export_heatmap(world, prefix);
+        }
+    }
+}
+
+/// Failing to save is reported but doesn't stop the run.
+fn save_checkpoint(world: &sim::World, scene: &str, path: &str) {
+    if let Err(e) = sim::checkpoint::Checkpoint::of(world, scene).save(path) {
+        eprintln!("{e}");
+    }
+}
+
+/// Prints how long the favoured routes are next to the shortest possible.
+fn export_heatmap(world: &sim::World, prefix: &str) {
+    let Some(heatmap) = &world.heatmap else {
+        return;
+    };
+    match sim::heatmap::export(heatmap, world, prefix) {
+        Ok(routes) => {
+            for (from, to, length, optimal) in routes {
+                println!(
+                    "emergent\t{}\t{from}\t{to}\t{length}\t{optimal}",
+                    world.time
+                );
+            }
+        }
+        Err(e) => eprintln!("exporting heatmap to {prefix}: {e}"),
+    }
+}
+
+/// How the world maps onto the pixel buffer.
+#[derive(Clone, Copy)]
+struct Scale {
+    px_per_unit: f32,
+    px_width: usize,
+}
+
+/// Resizes the pixel buffer to show the whole world as large as the window allows, keeping its
+/// aspect ratio; the rest of the window is left blank. The buffer is in logical pixels, which
+/// `pixels` scales up on high-DPI screens. -> None for a minimised window, which has no size
+fn fit(pixels: &mut Pixels, world_size: Vec2, window: &Window) -> Option<Scale> {
+    let size = window.inner_size();
+    if size.width == 0 || size.height == 0 {
+        return None;
+    }
+    let logical: LogicalSize<f32> = size.to_logical(window.scale_factor());
+    let px_per_unit = (logical.width / world_size.x).min(logical.height / world_size.y);
+    let [width, height] = world_size.map(|v| ((v * px_per_unit) as u32).max(1));
+    pixels
+        .resize_surface(size.width, size.height)
+        .expect("resizing surface failed");
+    pixels
+        .resize_buffer(width, height)
+        .expect("resizing buffer failed");
+    Some(Scale {
+        px_per_unit,
+        px_width: width as usize,
+    })
+}
+
+fn open_window(
+    event_loop: &EventLoop<()>,
+    hide_cursor: bool,
+    world_size: Vec2,
+) -> (Window, Pixels, Scale) {
+    let window = {
+        let size = LogicalSize::new(SCREEN_DIMS.0, SCREEN_DIMS.1);
+        WindowBuilder::new()
+            .with_inner_size(size)
+            .build(event_loop)
+            .expect("WindowBuilder failed")
+    };
+    if hide_cursor {
+        window.set_cursor_visible(false);
+    }
+
+    let mut pixels = {
+        let window_size = window.inner_size();
+        let surface_texture = SurfaceTexture::new(window_size.width, window_size.height, &window);
+        PixelsBuilder::new(SCREEN_DIMS.0, SCREEN_DIMS.1, surface_texture)
+            .build()
+            .expect("PixelsBuilder failed")
+    };
+    let scale = fit(&mut pixels, world_size, &window).expect("window has no size");
+    pixels.frame_mut().fill(0xff);
+    (window, pixels, scale)
+}
+
+/// Plays a recording back. Space pauses, up and down change speed, left and right seek, home
+/// restarts and escape quits.
+fn replay(path: &str, hide_cursor: bool) {
+    let recording = record::Recording::open(path).unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(1);
+    });
+    let Some(&end) = recording.times.last() else {
+        eprintln!("{path} has no frames");
+        std::process::exit(1);
+    };
+    let mut world = load_scene(&recording.scene);
+    let path = path.to_string();
+    eprintln!("{} frames, {end} s", recording.times.len());
+
+    let event_loop = EventLoop::new();
+    let (window, mut pixels, mut scale) = open_window(&event_loop, hide_cursor, world.world_size);
+
+    let mut time = recording.times[0];
+    let mut speed = 1.0;
+    let mut playing = true;
+    let mut last_loop = Instant::now();
+    event_loop.run(move |event, _, control_flow| match event {
+        Event::WindowEvent {
+            event:
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode: Some(key),
+                            ..
+                        },
+                    ..
+                },
+            ..
+        } => match key {
+            VirtualKeyCode::Space => playing = !playing,
+            VirtualKeyCode::Up => speed *= 2.0,
+            VirtualKeyCode::Down => speed /= 2.0,
+            VirtualKeyCode::Right => time = (time + REPLAY_SEEK).min(end),
+            VirtualKeyCode::Left => time = (time - REPLAY_SEEK).max(recording.times[0]),
+            VirtualKeyCode::Home => time = recording.times[0],
+            VirtualKeyCode::Escape => control_flow.set_exit(),
+            _ => {}
+        },
+        Event::WindowEvent {
+            event: WindowEvent::CloseRequested,
+            ..
+        } => control_flow.set_exit(),
+        Event::WindowEvent {
+            event: WindowEvent::Resized(_) | WindowEvent::ScaleFactorChanged { .. },
+            ..
+        } => {
+            if let Some(fitted) = fit(&mut pixels, world.world_size, &window) {
+                scale = fitted;
+            }
+        }
+        Event::MainEventsCleared => {
+            let now = Instant::now();
+            let mut delta = now - last_loop;
+            if delta < FRAME_TIME_MIN {
+                std::thread::sleep(FRAME_TIME_MIN - delta);
+                delta = FRAME_TIME_MIN;
+            }
+            last_loop = now;
+            if playing {
+                time = (time + speed * delta.as_secs_f32()).min(end);
+            }
+
+            recording.show(recording.frame_at(time), &mut world);
+            let frame = pixels.frame_mut();
+            frame
+                .chunks_mut(4)
+                .for_each(|px| px[0..3].copy_from_slice(&BACKGROUND));
+            world.render(frame, scale.px_per_unit, scale.px_width);
+            pixels.render().unwrap();
+            window.set_title(&format!(
+                "{path} {time:.1}/{end:.1} s x{speed}{}",
+                if playing { "" } else { " paused" }
+            ));
+        }
+        _ => {}
+    });
 }
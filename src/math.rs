@@ -1,7 +1,7 @@
 use std::ops::{Add, AddAssign, Div, DivAssign, Index, Mul, MulAssign, Neg, Sub, SubAssign};
 
-#[derive(Clone, Copy, Debug, serde::Deserialize)]
-#[serde(from = "(f32, f32)")]
+#[derive(Clone, Copy, Debug, serde::Deserialize, serde::Serialize)]
+#[serde(from = "(f32, f32)", into = "(f32, f32)")]
 pub struct Vec2 {
     pub x: f32,
     pub y: f32,
@@ -47,6 +47,12 @@ impl From<(f32, f32)> for Vec2 {
     }
 }
 
+impl From<Vec2> for (f32, f32) {
+    fn from(Vec2 { x, y }: Vec2) -> Self {
+        (x, y)
+    }
+}
+
 impl From<[f32; 2]> for Vec2 {
     fn from([x, y]: [f32; 2]) -> Self {
         Self { x, y }
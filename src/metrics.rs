@@ -0,0 +1,202 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+};
+
+use crate::sim::World;
+
+/// File format of the metrics log.
+#[derive(Clone, Copy)]
+pub enum Format {
+    /// one header line, then one comma separated row per sample
+    Csv,
+    /// one JSON object per sample
+    Jsonl,
+}
+
+/// Swarm statistics at one moment. Unknown values are NaN.
+pub struct Sample {
+    pub time: f32,
+    /// shortest trip any agent has made between targeted kinds, including agents since lost
+    pub best: f32,
+    /// over agents still around that have made a trip
+    pub mean_shortest: f32,
+    pub median_shortest: f32,
+    /// messages processed in the last tick
+    pub messages: usize,
+    /// trips completed between any two site kinds
+    pub trips: u32,
+    /// agents heading for each site kind
+    pub targeting: Vec<usize>,
+    /// best trip length for each pair of kinds, indexed `from * n_kinds + to`
+    pub routes: Vec<f32>,
+}
+
+impl Sample {
+    pub fn of(world: &World) -> Self {
+        let n_kinds = world.n_kinds();
+        let mut shortest: Vec<f32> = world
+            .agents
+            .iter()
+            .map(|a| a.shortest_dist)
+            .filter(|dist| dist.is_finite() && *dist < f32::MAX)
+            .collect();
+        shortest.sort_by(f32::total_cmp);
+        let mean_shortest = shortest.iter().sum::<f32>() / shortest.len() as f32;
+        let median_shortest = match shortest.len() {
+            0 => f32::NAN,
+            len if len % 2 == 0 => (shortest[len / 2 - 1] + shortest[len / 2]) / 2.0,
+            len => shortest[len / 2],
+        };
+
+        let mut targeting = vec![0; n_kinds];
+        for (kind, _) in world.agents.iter().filter_map(|a| a.state.target) {
+            if let Some(count) = targeting.get_mut(kind) {
+                *count += 1;
+            }
+        }
+
+        let routes = world.routes();
+        Self {
+            time: world.time,
+            best: Some(world.shortest_dist())
+                .filter(|&dist| dist < f32::MAX)
+                .unwrap_or(f32::NAN),
+            mean_shortest,
+            median_shortest,
+            messages: world.messages,
+            trips: routes.trips.iter().sum(),
+            targeting,
+            routes: routes
+                .best
+                .iter()
+                .map(|&best| if best.is_finite() { best } else { f32::NAN })
+                .collect(),
+        }
+    }
+}
+
+/// Writes a `Sample` every `interval` seconds of simulated time.
+pub struct Metrics {
+    out: BufWriter<File>,
+    format: Format,
+    interval: f32,
+    next: f32,
+    n_kinds: usize,
+}
+
+impl Metrics {
+    pub fn create(path: &str, format: Format, interval: f32, n_kinds: usize) -> Self {
+        let file = File::create(path).unwrap_or_else(|e| panic!("creating {path}: {e}"));
+        let mut metrics = Self {
+            out: BufWriter::new(file),
+            format,
+            interval,
+            next: 0.0,
+            n_kinds,
+        };
+        if let Format::Csv = format {
+            metrics.write_header();
+        }
+        metrics
+    }
+
+    fn route_pairs(&self) -> impl Iterator<Item = (usize, usize)> {
+        let n_kinds = self.n_kinds;
+        (0..n_kinds).flat_map(move |from| {
+            (0..n_kinds)
+                .filter(move |&to| to != from)
+                .map(move |to| (from, to))
+        })
+    }
+
+    fn write_header(&mut self) {
+        let mut cols: Vec<String> = [
+            "time",
+            "best",
+            "mean_shortest",
+            "median_shortest",
+            "messages",
+            "trips",
+        ]
+        .map(String::from)
+        .into();
+        cols.extend((0..self.n_kinds).map(|kind| format!("targeting_{kind}")));
+        cols.extend(
+            self.route_pairs()
+                .map(|(from, to)| format!("route_{from}_{to}")),
+        );
+        writeln!(self.out, "{}", cols.join(",")).unwrap();
+    }
+
+    /// Samples the world if the interval has passed since the last sample.
+    pub fn record(&mut self, world: &World) {
+        if world.time < self.next {
+            return;
+        }
+        self.next = world.time + self.interval;
+        let sample = Sample::of(world);
+        let routes: Vec<f32> = self
+            .route_pairs()
+            .map(|(from, to)| sample.routes[from * self.n_kinds + to])
+            .collect();
+
+        let line = match self.format {
+            Format::Csv => {
+                let mut cols = vec![
+                    sample.time.to_string(),
+                    csv_num(sample.best),
+                    csv_num(sample.mean_shortest),
+                    csv_num(sample.median_shortest),
+                    sample.messages.to_string(),
+                    sample.trips.to_string(),
+                ];
+                cols.extend(sample.targeting.iter().map(usize::to_string));
+                cols.extend(routes.into_iter().map(csv_num));
+                cols.join(",")
+            }
+            Format::Jsonl => {
+                let targeting: Vec<String> =
+                    sample.targeting.iter().map(usize::to_string).collect();
+                let routes: Vec<String> = self
+                    .route_pairs()
+                    .zip(routes)
+                    .map(|((from, to), best)| format!("\"{from}-{to}\":{}", json_num(best)))
+                    .collect();
+                format!(
+                    "{{\"time\":{},\"best\":{},\"mean_shortest\":{},\"median_shortest\":{},\
+                     \"messages\":{},\"trips\":{},\"targeting\":[{}],\"routes\":{{{}}}}}",
+                    sample.time,
+                    json_num(sample.best),
+                    json_num(sample.mean_shortest),
+                    json_num(sample.median_shortest),
+                    sample.messages,
+                    sample.trips,
+                    targeting.join(","),
+                    routes.join(","),
+                )
+            }
+        };
+        writeln!(self.out, "{line}").unwrap();
+        // The event loop never returns, so nothing is left buffered
+        self.out.flush().unwrap();
+    }
+}
+
+/// Unknown values are left empty.
+fn csv_num(v: f32) -> String {
+    if v.is_finite() {
+        v.to_string()
+    } else {
+        String::new()
+    }
+}
+
+/// Unknown values are null, as JSON has no NaN.
+fn json_num(v: f32) -> String {
+    if v.is_finite() {
+        v.to_string()
+    } else {
+        "null".to_string()
+    }
+}
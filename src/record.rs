@@ -0,0 +1,241 @@
+use std::{
+    f32::consts::PI,
+    fs::File,
+    io::{BufWriter, Write},
+};
+
+use crate::{
+    agent::{Agent, Failure},
+    math::Vec2,
+    sim::World,
+};
+
+// Little endian throughout:
+//   b"SANTREC1", u32 scene length, scene RON
+//   frames until the end of the file:
+//     f32 time
+//     u32 site count, per site: u8 present, f32 x, f32 y
+//     u32 agent count, per agent: u16 x, u16 y as fractions of the world size, u16 heading as a
+//       fraction of a turn, u8 target kind + 1 or 0 without one, u8 flags
+const MAGIC: &[u8; 8] = b"SANTREC1";
+const SCOUT: u8 = 1;
+const CRASHED: u8 = 2;
+const MUTE: u8 = 4;
+/// Most site kinds a recording can tell apart, as 0 is kept for agents without a target.
+pub const MAX_KINDS: usize = u8::MAX as usize;
+
+/// Writes the world's agents to a recording every tick, or at a fixed rate of simulated time.
+pub struct Recorder {
+    out: BufWriter<File>,
+    /// simulated time between frames; none to record every tick
+    interval: Option<f32>,
+    next: f32,
+}
+
+impl Recorder {
+    /// Starts a recording of `world`, loaded from `scene`, down-sampled to `fps` frames per
+    /// simulated second if given.
+    pub fn create(path: &str, world: &World, scene: &str, fps: Option<f32>) -> Self {
+        let n_kinds = world.n_kinds();
+        assert!(
+            n_kinds <= MAX_KINDS,
+            "recording {path}: recordings hold up to {MAX_KINDS} site kinds, not {n_kinds}",
+        );
+        let file = File::create(path).unwrap_or_else(|e| panic!("creating {path}: {e}"));
+        let mut out = BufWriter::new(file);
+        out.write_all(MAGIC).unwrap();
+        out.write_all(&(scene.len() as u32).to_le_bytes()).unwrap();
+        out.write_all(scene.as_bytes()).unwrap();
+        Self {
+            out,
+            interval: fps.map(|fps| 1.0 / fps),
+            next: 0.0,
+        }
+    }
+
+    /// Writes a frame if one is due.
+    pub fn record(&mut self, world: &World) {
+        if let Some(interval) = self.interval {
+            if world.time < self.next {
+                return;
+            }
+            while self.next <= world.time {
+                self.next += interval;
+            }
+        }
+
+        let mut buf = Vec::with_capacity(8 + 9 * world.sites.len() + 8 * world.agents.len());
+        buf.extend(world.time.to_le_bytes());
+        buf.extend((world.sites.len() as u32).to_le_bytes());
+        for site in &world.sites {
+            buf.push(site.present(world.time) as u8);
+            buf.extend(site.pos.x.to_le_bytes());
+            buf.extend(site.pos.y.to_le_bytes());
+        }
+
+        buf.extend((world.agents.len() as u32).to_le_bytes());
+        let quantize = |v: f32| (v.clamp(0.0, 1.0) * u16::MAX as f32).round() as u16;
+        for agent in &world.agents {
+            buf.extend(quantize(agent.pos.x / world.world_size.x).to_le_bytes());
+            buf.extend(quantize(agent.pos.y / world.world_size.y).to_le_bytes());
+            buf.extend(quantize(agent.dir.rem_euclid(2.0 * PI) / (2.0 * PI)).to_le_bytes());
+            let target = agent.state.target.map_or(0, |(kind, _)| {
+                u8::try_from(kind + 1).expect("more site kinds than a recording holds")
+            });
+            buf.push(target);
+            buf.push(
+                if agent.is_scout { SCOUT } else { 0 }
+                    | match agent.failure {
+                        Some(Failure::Crash) => CRASHED,
+                        Some(Failure::Radio) => MUTE,
+                        None => 0,
+                    },
+            );
+        }
+        self.out.write_all(&buf).unwrap();
+        // The event loop never returns, so nothing is left buffered
+        self.out.flush().unwrap();
+    }
+}
+
+/// A recording loaded for playback. Frames are decoded as they are shown.
+pub struct Recording {
+    pub scene: String,
+    data: Vec<u8>,
+    /// byte offset of each frame
+    frames: Vec<usize>,
+    /// time of each frame
+    pub times: Vec<f32>,
+}
+
+/// Reads little endian values from a byte slice, `None` past its end.
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn bytes<const N: usize>(&mut self) -> Option<[u8; N]> {
+        let bytes = self.data.get(self.pos..self.pos + N)?.try_into().ok()?;
+        self.pos += N;
+        Some(bytes)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.bytes::<1>().map(|[v]| v)
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        self.bytes().map(u16::from_le_bytes)
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        self.bytes().map(u32::from_le_bytes)
+    }
+
+    fn f32(&mut self) -> Option<f32> {
+        self.bytes().map(f32::from_le_bytes)
+    }
+
+    /// Skips a frame, returning its time.
+    fn skip_frame(&mut self) -> Option<f32> {
+        let time = self.f32()?;
+        let n_sites = self.u32()? as usize;
+        self.pos += 9 * n_sites;
+        let n_agents = self.u32()? as usize;
+        self.pos += 8 * n_agents;
+        (self.pos <= self.data.len()).then_some(time)
+    }
+}
+
+impl Recording {
+    pub fn open(path: &str) -> Result<Self, String> {
+        let data = std::fs::read(path).map_err(|e| format!("reading {path}: {e}"))?;
+        let mut reader = Reader {
+            data: &data,
+            pos: 0,
+        };
+        if reader.bytes::<8>().as_ref() != Some(MAGIC) {
+            return Err(format!("{path} is not a recording"));
+        }
+        let scene_len = reader.u32().ok_or("truncated header")? as usize;
+        let scene = data
+            .get(reader.pos..reader.pos + scene_len)
+            .and_then(|scene| String::from_utf8(scene.to_vec()).ok())
+            .ok_or("truncated header")?;
+        reader.pos += scene_len;
+
+        // A frame cut short by an interrupted run is dropped
+        let mut frames = Vec::new();
+        let mut times = Vec::new();
+        loop {
+            let start = reader.pos;
+            let Some(time) = reader.skip_frame() else {
+                break;
+            };
+            frames.push(start);
+            times.push(time);
+        }
+
+        Ok(Self {
+            scene,
+            data,
+            frames,
+            times,
+        })
+    }
+
+    /// Index of the last frame at or before `time`.
+    pub fn frame_at(&self, time: f32) -> usize {
+        self.times.partition_point(|&t| t <= time).saturating_sub(1)
+    }
+
+    /// Puts the world's sites and agents where they were in frame `idx`.
+    pub fn show(&self, idx: usize, world: &mut World) {
+        let mut reader = Reader {
+            data: &self.data,
+            pos: self.frames[idx],
+        };
+        // Frames were checked for length on loading
+        world.time = reader.f32().unwrap();
+
+        let n_sites = reader.u32().unwrap() as usize;
+        for id in 0..n_sites {
+            let present = reader.u8().unwrap() != 0;
+            let pos = Vec2::new(reader.f32().unwrap(), reader.f32().unwrap());
+            if let Some(site) = world.sites.get_mut(id) {
+                site.pos = pos;
+                // Presence is replayed rather than worked out from the timeline and visits
+                site.active.clear();
+                site.visits = (!present).then_some(0);
+            }
+        }
+
+        let n_agents = reader.u32().unwrap() as usize;
+        let mut rng = rand::thread_rng();
+        world
+            .agents
+            .resize_with(n_agents, || Agent::random(Vec2::new(0.0, 0.0), &mut rng));
+        let unquantize = |v: u16| v as f32 / u16::MAX as f32;
+        for agent in &mut world.agents {
+            agent.pos = Vec2::new(
+                unquantize(reader.u16().unwrap()) * world.world_size.x,
+                unquantize(reader.u16().unwrap()) * world.world_size.y,
+            );
+            agent.dir = unquantize(reader.u16().unwrap()) * 2.0 * PI;
+            agent.state.target = match reader.u8().unwrap() {
+                0 => None,
+                kind => Some((kind as usize - 1, 0.0)),
+            };
+            let flags = reader.u8().unwrap();
+            agent.is_scout = flags & SCOUT != 0;
+            agent.failure = if flags & CRASHED != 0 {
+                Some(Failure::Crash)
+            } else if flags & MUTE != 0 {
+                Some(Failure::Radio)
+            } else {
+                None
+            };
+        }
+    }
+}
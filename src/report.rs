@@ -0,0 +1,112 @@
+use crate::sim::World;
+
+/// Tab separated progress lines on stdout, printed as values improve or change. Times are
+/// seconds since the run started: wall clock in a window, simulated when headless.
+pub struct Reporter {
+    n_sites: usize,
+    shortest_dist: f32,
+    first_dist: Option<f32>,
+    best_tour: f32,
+    best_routes: Vec<f32>,
+    census: crate::sim::population::Census,
+    last_congestion: u64,
+    last_service: u64,
+}
+
+impl Reporter {
+    pub fn new(world: &World) -> Self {
+        let n_sites = world.n_kinds();
+        Self {
+            n_sites,
+            shortest_dist: f32::MAX,
+            first_dist: None,
+            best_tour: f32::MAX,
+            best_routes: vec![f32::INFINITY; n_sites * n_sites],
+            census: world.census(),
+            last_congestion: 0,
+            last_service: 0,
+        }
+    }
+
+    pub fn report(&mut self, world: &World, elapsed: f32) {
+        let n_sites = self.n_sites;
+
+        let new_shortest_dist = world.shortest_dist();
+        if new_shortest_dist < self.shortest_dist {
+            let shortest_dist = new_shortest_dist;
+            self.shortest_dist = shortest_dist;
+            println!("{elapsed}\t{shortest_dist}");
+
+            let first_dist = *self.first_dist.get_or_insert(shortest_dist);
+            if world.energy.is_some() && shortest_dist < first_dist {
+                let spent = world.energy_spent();
+                println!(
+                    "energy\t{elapsed}\t{spent}\t{}",
+                    spent / (first_dist - shortest_dist),
+                );
+            }
+        }
+
+        let new_best_tour = world.best_tour();
+        if new_best_tour < self.best_tour {
+            self.best_tour = new_best_tour;
+            println!("tour\t{elapsed}\t{}", self.best_tour);
+        }
+
+        let routes = world.routes();
+        if routes
+            .best
+            .iter()
+            .zip(&self.best_routes)
+            .any(|(new, old)| new < old)
+        {
+            self.best_routes.clone_from(&routes.best);
+            for from in 0..n_sites {
+                for to in (0..n_sites).filter(|&to| routes.get(from, to).0.is_finite()) {
+                    let (best, latest) = routes.get(from, to);
+                    println!("routes\t{elapsed}\t{from}\t{to}\t{best}\t{latest}");
+                }
+            }
+        }
+
+        let new_census = world.census();
+        if new_census != self.census {
+            self.census = new_census;
+            let census = &self.census;
+            // Best trip among agents that are still working
+            let working_dist = world
+                .agents
+                .iter()
+                .filter(|a| a.failure.is_none())
+                .map(|a| a.shortest_dist)
+                .fold(f32::MAX, f32::min);
+            println!(
+                "population\t{elapsed}\t{}\t{}\t{}\t{}\t{working_dist}",
+                census.working, census.crashed, census.mute, census.spawned,
+            );
+        }
+
+        let secs = elapsed as u64;
+        if world.crowding.is_some() && secs > self.last_congestion {
+            self.last_congestion = secs;
+            let queues: Vec<String> = world.congestion().iter().map(usize::to_string).collect();
+            let contacts = world.agents.iter().filter(|a| a.contacts > 0).count();
+            println!("congestion\t{elapsed}\t{contacts}\t{}", queues.join("\t"));
+        }
+
+        if world.sites.iter().any(|site| site.queues()) && secs > self.last_service {
+            self.last_service = secs;
+            for (id, site) in world.sites.iter().enumerate().filter(|(_, s)| s.queues()) {
+                let desk = &site.desk;
+                println!(
+                    "service\t{elapsed}\t{id}\t{}\t{}\t{}\t{}\t{}",
+                    desk.served,
+                    desk.serving,
+                    desk.waiting.len(),
+                    desk.mean_wait(),
+                    desk.served as f32 / world.time,
+                );
+            }
+        }
+    }
+}
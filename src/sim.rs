@@ -1,14 +1,34 @@
+pub mod checkpoint;
+pub mod crowding;
+pub mod energy;
+pub mod field;
+pub mod flow;
+pub mod geodesic;
+pub mod heatmap;
 pub mod obstacle;
+pub mod population;
 pub mod render;
 pub mod site;
+pub mod spatial;
+pub mod terrain;
+pub mod tour;
 
-use crate::agent::{Agent, Message};
+use crate::agent::{Agent, Failure, Forgetting, Message, Messaging, Routes, SiteState, Visit};
+use crate::behaviour::{self, Behaviour};
 use crate::math::Vec2;
+use crowding::Crowding;
+use energy::{Energy, Recharge};
+use field::Field;
+use flow::Flow;
 use obstacle::Obstacle;
+use population::{Census, Lost, Population};
 use render::Renderable;
-use site::Site;
+use site::{Nest, Site};
+use terrain::{Odometry, Terrain};
+use tour::Tour;
 
-use rand::Rng;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
 
 #[derive(serde::Deserialize)]
 pub struct World {
@@ -18,56 +38,340 @@ pub struct World {
     pub site_kinds: Vec<[u8; 3]>,
     #[serde(deserialize_with = "obstacle::deser_obstacles")]
     pub obstacles: Vec<Box<dyn Obstacle>>,
+    #[serde(default)]
+    pub terrain: Vec<Terrain>,
+    #[serde(default)]
+    pub odometry: Odometry,
+    #[serde(default)]
+    pub flow: Option<Flow>,
     pub world_size: Vec2,
+    #[serde(default)]
+    pub nest: Option<Nest>,
+    #[serde(default)]
+    pub energy: Option<Energy>,
+    #[serde(default)]
+    pub population: Population,
+    #[serde(default)]
+    pub crowding: Option<Crowding>,
+    #[serde(default)]
+    pub messaging: Messaging,
+    #[serde(default)]
+    pub comm: Comm,
+    #[serde(default)]
+    pub forgetting: Forgetting,
+    #[serde(default)]
+    pub tour: Tour,
+    #[serde(
+        default = "behaviour::default_behaviour",
+        deserialize_with = "behaviour::deser_behaviour"
+    )]
+    pub behaviour: Box<dyn Behaviour>,
+    /// pheromone field, present in `Comm::Pheromone` and `Comm::Aco` scenes once the world has
+    /// been updated
+    #[serde(skip)]
+    pub field: Option<Field>,
+    /// seconds of simulated time
+    #[serde(skip)]
+    pub time: f32,
+    #[serde(skip)]
+    next_chain: u32,
+    /// fractional agents owed by `Population::spawn_rate`
+    #[serde(skip)]
+    spawn_debt: f32,
+    #[serde(skip)]
+    spawned: usize,
+    /// agents crashed so far, including those whose slots have since gone to new agents
+    #[serde(skip)]
+    crashed: usize,
+    /// what agents made before their slots went to new agents
+    #[serde(skip)]
+    lost: Lost,
     #[serde(skip)]
     msg_queue: std::collections::VecDeque<Message>,
+    /// messages processed in the last update
+    #[serde(skip)]
+    pub messages: usize,
+    /// where targeting workers have been, once enabled with `heatmap::new`
+    #[serde(skip)]
+    pub heatmap: Option<Field>,
+    /// source of the world's random events; agents have their own
+    #[serde(skip, default = "ChaCha8Rng::from_entropy")]
+    pub rng: ChaCha8Rng,
+}
+
+/// How agents share site information.
+#[derive(Clone, Copy, Default, serde::Deserialize)]
+pub enum Comm {
+    /// agents relay `Message`s to each other within `Agent::comm`
+    #[default]
+    Direct,
+    /// agents mark and read a diffusing, evaporating world-space field
+    Pheromone(PheromoneParams),
+    /// classic ant colony optimization: agents lay pheromone on their way back from a site and
+    /// follow it probabilistically
+    Aco(AcoParams),
+}
+
+impl Comm {
+    fn field_cell(&self) -> Option<f32> {
+        match self {
+            Comm::Direct => None,
+            Comm::Pheromone(params) => Some(params.cell),
+            Comm::Aco(params) => Some(params.cell),
+        }
+    }
+}
+
+#[derive(Clone, Copy, serde::Deserialize)]
+#[serde(default)]
+pub struct PheromoneParams {
+    /// grid cell side length
+    pub cell: f32,
+    /// fraction of the difference to the neighbour mean exchanged per second
+    pub diffusion: f32,
+    /// fraction of the marker lost per second
+    pub evaporation: f32,
+    /// distance over which a marker falls to 1/e; a marker for distance `d` is `exp(-d / scale)`
+    pub scale: f32,
+}
+
+impl Default for PheromoneParams {
+    fn default() -> Self {
+        Self {
+            cell: 0.1,
+            diffusion: 2.0,
+            evaporation: 0.1,
+            scale: 2.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, serde::Deserialize)]
+#[serde(default)]
+pub struct AcoParams {
+    /// grid cell side length
+    pub cell: f32,
+    /// pheromone laid per unit distance walked
+    pub deposit: f32,
+    /// distance from the last site over which an agent's deposits fall to 1/e
+    pub fade: f32,
+    /// fraction of the difference to the neighbour mean exchanged per second
+    pub diffusion: f32,
+    /// fraction of the pheromone lost per second
+    pub evaporation: f32,
+    /// exponent applied to pheromone concentration when choosing a heading
+    pub alpha: f32,
+    /// angle either side of the heading at which pheromone is sensed
+    pub sense_angle: f32,
+    /// distance ahead at which pheromone is sensed
+    pub sense_dist: f32,
+    /// turn speed towards the chosen side, in radians per second
+    pub steer: f32,
+}
+
+impl Default for AcoParams {
+    fn default() -> Self {
+        Self {
+            cell: 0.1,
+            deposit: 10.0,
+            fade: 8.0,
+            diffusion: 0.5,
+            evaporation: 0.05,
+            alpha: 2.0,
+            sense_angle: 30f32.to_radians(),
+            sense_dist: 0.3,
+            steer: 6.0,
+        }
+    }
 }
 
 impl World {
-    pub fn new() -> Self {
-        let mut rng = rand::thread_rng();
-        let empty_state = crate::agent::State {
-            sites: Default::default(),
-            target: None,
+    /// The default swarm, bunched up in the top right corner.
+    pub fn random_agents(rng: &mut impl Rng) -> Vec<Agent> {
+        (0..2000)
+            .map(|_| {
+                // Bottom Middle
+                // let x_coord = rng.gen_range(7.5..=8.5);
+                // let y_coord = rng.gen_range(8.5..=9.5);
+                // Everywhere
+                // let x_coord = rng.gen_range(0.0..=16.0);
+                // let y_coord = rng.gen_range(0.0..=10.0);
+                // Top Right
+                let x_coord = rng.gen_range(15.0..=16.0);
+                let y_coord = rng.gen_range(0.0..=1.0);
+                Agent::random((x_coord, y_coord).into(), rng)
+            })
+            .collect()
+    }
+
+    /// Gives an agent from `Agent::random` what it needs to know about this world.
+    pub fn setup_agent(&self, agent: &mut Agent) {
+        let n_kinds = self.n_kinds();
+        agent.state.sites = self
+            .tour
+            .initial_targets(n_kinds)
+            .into_iter()
+            .map(|targeting| SiteState {
+                targeting,
+                ..Default::default()
+            })
+            .collect();
+        if let Tour::Open = self.tour {
+            agent.state.sites[agent.rng.gen_range(0..=1)].targeting = false;
+        }
+        agent.routes = Routes::new(n_kinds);
+        if let Some(energy) = &self.energy {
+            agent.energy = energy.capacity;
+        }
+    }
+
+    pub fn census(&self) -> Census {
+        let count = |failure| {
+            self.agents
+                .iter()
+                .filter(|agent| agent.failure == failure)
+                .count()
         };
-        let agents = (0..2000).map(|_| {
-            // Bottom Middle
-            // let x_coord = rng.gen_range(7.5..=8.5);
-            // let y_coord = rng.gen_range(8.5..=9.5);
-            // Everywhere
-            // let x_coord = rng.gen_range(0.0..=16.0);
-            // let y_coord = rng.gen_range(0.0..=10.0);
-            // Top Right
-            let x_coord = rng.gen_range(15.0..=16.0);
-            let y_coord = rng.gen_range(0.0..=1.0);
-            Agent {
-                pos: (x_coord, y_coord).into(),
-                dir: rng.gen_range(0.0..2.0 * std::f32::consts::PI),
-                state: empty_state.clone(),
-                // state: {
-                //     let mut state = empty_state.clone();
-                //     state.sites[rng.gen_range(0..=1)].1 = true;
-                //     state
-                // },
-                speed: rng.gen_range(0.1..=1.5),
-                // speed: rng.gen_range(3.5..=4.5),
-                turn: 100.0f32.to_radians(),
-                comm: 0.8,
-                // comm: 2.0,
-                obs_dist: 0.4,
-                is_scout: rng.gen_bool(0.5),
-                shortest_dist: f32::MAX,
-                current_dist: f32::NAN,
+        Census {
+            working: count(None),
+            crashed: self.crashed,
+            mute: count(Some(Failure::Radio)),
+            spawned: self.spawned,
+        }
+    }
+
+    /// Queue length at each of `Crowding::bottlenecks`.
+    pub fn congestion(&self) -> Vec<usize> {
+        self.crowding
+            .iter()
+            .flat_map(|crowding| &crowding.bottlenecks)
+            .map(|bottleneck| bottleneck.queue(&self.agents))
+            .collect()
+    }
+
+    /// Checks for settings that parse but that the simulation can't run with.
+    pub fn check(&self) -> Result<(), String> {
+        for site in &self.sites {
+            site.motion.check()?;
+        }
+        self.forgetting.check()?;
+        // Agents would be stuck in, or thrown back out of, ground that isn't passable
+        if let Some(region) = self
+            .terrain
+            .iter()
+            .find(|region| region.speed <= 0.0 || region.speed.is_nan())
+        {
+            return Err(format!(
+                "terrain speeds must be positive, not {}",
+                region.speed
+            ));
+        }
+        if let (Some(energy), None) = (&self.energy, &self.nest) {
+            if let Recharge::Nest | Recharge::Both = energy.recharge_at {
+                return Err("agents recharge at the nest, but there isn't one".into());
             }
-        });
+        }
+        self.tour.check(self.n_kinds())
+    }
 
-        Self {
-            agents: agents.collect(),
-            sites: Vec::new(),
-            site_kinds: Vec::new(),
-            obstacles: Vec::new(),
-            world_size: (16.0, 10.0).into(),
-            msg_queue: Default::default(),
+    /// Number of site kinds in use.
+    pub fn n_kinds(&self) -> usize {
+        self.sites
+            .iter()
+            .map(|site| site.kind + 1)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Obstacle-avoiding distances between the nearest sites of every pair of kinds, measured
+    /// edge to edge on the `geodesic::CELL` grid with `self.odometry`.
+    pub fn kind_distances(&self) -> Vec<Vec<f32>> {
+        let grid = Field::new(
+            self.world_size,
+            geodesic::CELL,
+            0,
+            self.obstacles.iter().map(Box::as_ref),
+        );
+        let weights: Vec<f32> = (0..grid.blocked.len())
+            .map(|idx| {
+                let speed = terrain::speed_at(&self.terrain, grid.center_of(idx));
+                self.odometry.cost_scale(speed)
+            })
+            .collect();
+        let cells: Vec<Vec<usize>> = (0..self.n_kinds())
+            .map(|kind| {
+                self.sites
+                    .iter()
+                    .filter(|site| site.kind == kind)
+                    .flat_map(|site| geodesic::site_cells(&grid, site))
+                    .collect()
+            })
+            .collect();
+
+        cells
+            .iter()
+            .map(|sources| {
+                let dist = geodesic::distances(&grid, sources, &weights);
+                cells
+                    .iter()
+                    .map(|dests| {
+                        dests
+                            .iter()
+                            .map(|&idx| dist[idx])
+                            .fold(f32::INFINITY, f32::min)
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Route statistics of the whole swarm: the best trip any agent has made between each pair
+    /// of kinds, and the mean of the latest trips of the agents still around.
+    pub fn routes(&self) -> Routes {
+        let n_kinds = self.n_kinds();
+        let mut routes = Routes::new(n_kinds);
+        if self.lost.routes.n_kinds == n_kinds {
+            routes.best.clone_from(&self.lost.routes.best);
+            routes.trips.clone_from(&self.lost.routes.trips);
+        }
+        let mut counts = vec![0u32; n_kinds * n_kinds];
+        let mut sums = vec![0.0; n_kinds * n_kinds];
+        for agent in self.agents.iter().filter(|a| a.routes.n_kinds == n_kinds) {
+            for idx in 0..n_kinds * n_kinds {
+                routes.best[idx] = routes.best[idx].min(agent.routes.best[idx]);
+                routes.trips[idx] += agent.routes.trips[idx];
+                if agent.routes.latest[idx].is_finite() {
+                    counts[idx] += 1;
+                    sums[idx] += agent.routes.latest[idx];
+                }
+            }
+        }
+        for ((latest, count), sum) in routes.latest.iter_mut().zip(counts).zip(sums) {
+            if count > 0 {
+                *latest = sum / count as f32;
+            }
+        }
+        routes
+    }
+
+    /// Shortest tour allowed by `self.tour`, if it records tours and is small enough to solve.
+    /// -> (length, kinds in visiting order)
+    pub fn optimal_tour(&self) -> Option<(f32, Vec<usize>)> {
+        match &self.tour {
+            Tour::Open => None,
+            Tour::Any => {
+                let kinds: Vec<usize> = (0..self.n_kinds()).collect();
+                tour::held_karp(&self.kind_distances(), &kinds)
+            }
+            Tour::Ordered(order) => {
+                let dist = self.kind_distances();
+                let len = order
+                    .iter()
+                    .zip(order.iter().cycle().skip(1))
+                    .map(|(&a, &b)| dist.get(a)?.get(b).copied())
+                    .sum::<Option<f32>>()?;
+                Some((len, order.clone()))
+            }
         }
     }
 
@@ -80,11 +384,23 @@ impl World {
                 px_width,
             };
 
+        for region in &self.terrain {
+            region.render(&mut args);
+        }
+
         for obstacle in &self.obstacles {
             obstacle.render(&mut args);
         }
 
-        for site in &self.sites {
+        if let Some(nest) = &self.nest {
+            nest.render(&mut args);
+        }
+
+        if let Some(field) = &self.field {
+            field.render(&mut args);
+        }
+
+        for site in self.sites.iter().filter(|site| site.present(self.time)) {
             site.render(&mut args);
         }
 
@@ -96,23 +412,342 @@ impl World {
     }
 
     pub fn update(&mut self, delta: f32) {
+        self.time += delta;
+        for site in &mut self.sites {
+            site.update(self.time);
+        }
+        self.update_population(delta);
         self.agents.iter_mut().for_each(|agent| {
-            agent.step(delta, self.obstacles.iter().map(Box::as_ref));
-            agent.contain(self.world_size);
-            agent.avoid_obstacles(self.obstacles.iter().map(Box::as_ref));
+            if agent.failure == Some(Failure::Crash) {
+                return;
+            }
+            agent.forget(self.forgetting, self.time, delta);
+            // Agents at a site wait there until served
+            if agent.visit.is_some() {
+                return;
+            }
+            // Agents without energy stand still
+            if agent.energy <= 0.0 {
+                return;
+            }
+            let speed = terrain::speed_at(&self.terrain, agent.pos);
+            agent.speed_scale = speed;
+            agent.cost_scale = self.odometry.cost_scale(speed);
+            let own_speed = self.behaviour.speed(agent) * speed;
+            // Agents low on energy only move as far as what's left takes them
+            let mut move_time = delta;
+            if let Some(energy) = &self.energy {
+                let cost = energy.move_cost * own_speed * delta;
+                if cost > agent.energy {
+                    move_time *= agent.energy / cost;
+                }
+            }
+            if let Some(flow) = &self.flow {
+                agent.drift = flow.field.velocity(agent.pos);
+                // Charge for the track over the ground, the same either way along it
+                if !flow.asymmetric && own_speed > 0.0 {
+                    let heading = Vec2::new(agent.dir.cos(), agent.dir.sin());
+                    let ground_speed = (own_speed * heading + agent.drift).mag();
+                    agent.cost_scale *= ground_speed / own_speed;
+                }
+            }
+            agent.step(
+                move_time,
+                self.obstacles.iter().map(Box::as_ref),
+                self.behaviour.as_ref(),
+            );
+            if let Some(energy) = &self.energy {
+                agent.spend(energy.move_cost * own_speed * move_time);
+            }
+            self.behaviour
+                .steer(agent, delta, self.world_size, &self.obstacles);
         });
-        self.msg_queue
-            .extend(self.sites.iter().map(Site::collision_msg));
-        // self.msg_queue
-        //     .extend(self.sites.iter().map(Site::visibility_msg));
+        if let Some(mut map) = self.heatmap.take() {
+            heatmap::accumulate(&mut map, self, delta);
+            self.heatmap = Some(map);
+        }
+        if let Some(crowding) = &self.crowding {
+            crowding::separate(
+                &mut self.agents,
+                crowding,
+                self.world_size,
+                self.obstacles.iter().map(Box::as_ref),
+            );
+        }
+        if let Some(energy) = self.energy {
+            self.recharge(delta, energy);
+        }
+        if let (Some(cell), None) = (self.comm.field_cell(), &self.field) {
+            self.field = Some(Field::new(
+                self.world_size,
+                cell,
+                self.site_kinds.len(),
+                self.obstacles.iter().map(Box::as_ref),
+            ));
+        }
+        match self.comm {
+            Comm::Direct => {}
+            Comm::Pheromone(params) => self.update_field(delta, params),
+            Comm::Aco(params) => self.update_aco(delta, params),
+        }
+        self.serve();
+        for (id, site) in self.sites.iter().enumerate() {
+            if !site.present(self.time) {
+                continue;
+            }
+            let mut msg = site.collision_msg(id, self.time);
+            // let mut msg = site.visibility_msg(id, self.time);
+            if self.messaging.chains {
+                msg.chain = Some(self.next_chain);
+                self.next_chain = self.next_chain.wrapping_add(1);
+            }
+            self.msg_queue.push_back(msg);
+        }
+        self.messages = 0;
         while let Some(msg) = self.msg_queue.pop_front() {
+            self.messages += 1;
             self.process_message(msg);
         }
     }
 
-    fn process_message(&mut self, msg: Message) {
-        let sq_range = msg.range.powi(2);
+    /// Releases agents whose service is over and admits waiting ones in their place, who arrive
+    /// as they are admitted. Agents being served are kept on the site and queueing ones around
+    /// its rim, so neither loses contact with a site that moves.
+    fn serve(&mut self) {
+        for (idx, agent) in self.agents.iter_mut().enumerate() {
+            let Some(visit) = agent.visit else {
+                continue;
+            };
+            let site = &mut self.sites[match visit {
+                Visit::Queued { site, .. } | Visit::Served { site, .. } => site,
+            }];
+            let gone = !site.present(self.time) || agent.failure.is_some();
+            match visit {
+                Visit::Served { until, .. } if gone || until <= self.time => {
+                    site.desk.serving -= 1;
+                    if !gone {
+                        site.desk.served += 1;
+                    }
+                    agent.visit = None;
+                }
+                Visit::Queued { .. } if gone => {
+                    site.desk.waiting.retain(|&waiting| waiting != idx);
+                    agent.visit = None;
+                }
+                _ => {}
+            }
+        }
+
+        for (id, site) in self.sites.iter_mut().enumerate() {
+            while site.present(self.time) && !site.full() {
+                let Some(idx) = site.desk.waiting.pop_front() else {
+                    break;
+                };
+                let agent = &mut self.agents[idx];
+                let Some(Visit::Queued { since, .. }) = agent.visit else {
+                    continue;
+                };
+                site.desk.serving += 1;
+                site.desk.admitted += 1;
+                site.desk.waited += self.time - since;
+                agent.visit = Some(Visit::Served {
+                    site: id,
+                    until: self.time + site.service,
+                });
+
+                let arrivals = agent.arrivals;
+                let new_msg = agent.inform(
+                    site.collision_msg(id, self.time),
+                    self.time,
+                    self.messaging,
+                    &self.tour,
+                    self.behaviour.as_ref(),
+                );
+                if agent.arrivals > arrivals {
+                    site.visited += 1;
+                }
+                if let (Some(new_msg), Comm::Direct) = (new_msg, self.comm) {
+                    self.msg_queue.push_back(new_msg);
+                }
+            }
+        }
+
         for agent in &mut self.agents {
+            let (site, rim) = match agent.visit {
+                Some(Visit::Served { site, .. }) => (site, false),
+                Some(Visit::Queued { site, .. }) => (site, true),
+                None => continue,
+            };
+            let site = &self.sites[site];
+            let offset = agent.pos - site.pos;
+            let dist = offset.mag();
+            let pos = if rim || dist > site.size {
+                // Agents on the site's centre queue on an arbitrary but consistent side
+                let away = if dist > 0.0 {
+                    offset / dist
+                } else {
+                    Vec2::new(1.0, 0.0)
+                };
+                site.pos + site.size * away
+            } else {
+                agent.pos
+            };
+            if !obstacle::blocked(self.obstacles.iter().map(Box::as_ref), pos) {
+                agent.pos = pos;
+            }
+        }
+    }
+
+    fn update_population(&mut self, delta: f32) {
+        if let Some(nest) = &self.nest {
+            self.spawn_debt += self.population.spawn_rate * delta;
+            while self.spawn_debt >= 1.0 {
+                self.spawn_debt -= 1.0;
+                let working = self.agents.iter().filter(|a| a.failure.is_none()).count();
+                if self.population.max_agents.is_some_and(|max| working >= max) {
+                    continue;
+                }
+                let angle = self.rng.gen_range(0.0..2.0 * std::f32::consts::PI);
+                let radius = nest.size * self.rng.gen::<f32>().sqrt();
+                let pos = nest.pos + radius * Vec2::new(angle.cos(), angle.sin());
+                let mut agent = Agent::random(pos, &mut self.rng);
+                self.setup_agent(&mut agent);
+                // New agents take the place of wrecks so the swarm doesn't grow without bound;
+                // a wreck still at a site waits for `serve` to take it off the desk first
+                let wreck = self
+                    .agents
+                    .iter()
+                    .position(|a| a.failure == Some(Failure::Crash) && a.visit.is_none());
+                match wreck {
+                    Some(idx) => {
+                        let wreck = std::mem::replace(&mut self.agents[idx], agent);
+                        self.lost.add(&wreck);
+                    }
+                    None => self.agents.push(agent),
+                }
+                self.spawned += 1;
+            }
+        }
+
+        let rng = &mut self.rng;
+        let random_failure = (self.population.failure_rate * delta).min(1.0);
+        for agent in self.agents.iter_mut().filter(|a| a.failure.is_none()) {
+            // Ticks cover `[time - delta, time)`, so events at time zero fire on the first one
+            let scheduled = self.population.mass_failures.iter().find(|event| {
+                self.time - delta <= event.time
+                    && event.time < self.time
+                    && (agent.pos - event.center).sq_mag() <= event.radius.powi(2)
+                    && rng.gen_bool(event.fraction.clamp(0.0, 1.0) as f64)
+            });
+            if let Some(event) = scheduled {
+                agent.failure = Some(event.failure);
+            } else if random_failure > 0.0 && rng.gen_bool(random_failure as f64) {
+                agent.failure = Some(
+                    if rng.gen_bool(self.population.radio_share.clamp(0.0, 1.0) as f64) {
+                        Failure::Radio
+                    } else {
+                        Failure::Crash
+                    },
+                );
+            }
+            if agent.failure == Some(Failure::Crash) {
+                self.crashed += 1;
+            }
+        }
+    }
+
+    fn recharge(&mut self, delta: f32, energy: Energy) {
+        let working = self
+            .agents
+            .iter_mut()
+            .filter(|a| a.failure != Some(Failure::Crash));
+        for agent in working {
+            let at_site = || {
+                self.sites.iter().any(|site| {
+                    site.present(self.time) && (agent.pos - site.pos).sq_mag() <= site.size.powi(2)
+                })
+            };
+            let at_nest = || {
+                self.nest
+                    .as_ref()
+                    .is_some_and(|nest| nest.contains(agent.pos))
+            };
+            let charging = match energy.recharge_at {
+                Recharge::Sites => at_site(),
+                Recharge::Nest => at_nest(),
+                Recharge::Both => at_site() || at_nest(),
+            };
+            if charging {
+                agent.energy = (agent.energy + energy.recharge * delta).min(energy.capacity);
+            }
+        }
+    }
+
+    /// Energy used by the whole swarm so far.
+    pub fn energy_spent(&self) -> f32 {
+        self.lost.energy_spent + self.agents.iter().map(|a| a.energy_spent).sum::<f32>()
+    }
+
+    /// Best trip any agent has made between two kinds; `f32::MAX` until one has.
+    pub fn shortest_dist(&self) -> f32 {
+        self.agents
+            .iter()
+            .map(|a| a.shortest_dist)
+            .fold(self.lost.shortest_dist, f32::min)
+    }
+
+    /// Best tour any agent has made; `f32::MAX` until one has.
+    pub fn best_tour(&self) -> f32 {
+        self.agents
+            .iter()
+            .map(|a| a.best_tour)
+            .fold(self.lost.best_tour, f32::min)
+    }
+
+    fn update_field(&mut self, delta: f32, params: PheromoneParams) {
+        let Some(field) = &mut self.field else {
+            return;
+        };
+
+        field.diffuse(delta, params.diffusion, params.evaporation);
+        for site in self.sites.iter().filter(|site| site.present(self.time)) {
+            field.deposit_circle(site.kind, site.pos, site.size, 1.0);
+        }
+        let working = self
+            .agents
+            .iter_mut()
+            .filter(|a| a.failure != Some(Failure::Crash));
+        for agent in working {
+            agent.sense(field, params.scale, self.time);
+        }
+    }
+
+    fn update_aco(&mut self, delta: f32, params: AcoParams) {
+        let Some(field) = &mut self.field else {
+            return;
+        };
+
+        field.diffuse(delta, params.diffusion, params.evaporation);
+        let working = self
+            .agents
+            .iter_mut()
+            .filter(|a| a.failure != Some(Failure::Crash));
+        for agent in working {
+            let speed = self.behaviour.speed(agent) * agent.speed_scale;
+            agent.forage(field, params, speed, delta);
+        }
+    }
+
+    /// Delivers a message to every agent in range that can see its source, queueing their relays
+    /// for the rest of the update.
+    pub fn process_message(&mut self, msg: Message) {
+        let sq_range = msg.range.powi(2);
+        for (idx, agent) in self
+            .agents
+            .iter_mut()
+            .enumerate()
+            .filter(|(_, a)| a.failure.is_none())
+        {
             let abs_diff = [0, 1].map(|i| (agent.pos[i] - msg.source[i]).abs());
             // Outside bounding box
             if abs_diff.into_iter().any(|v| v > msg.range)
@@ -125,14 +760,61 @@ impl World {
             if self
                 .obstacles
                 .iter()
-                .flat_map(|obs| obs.intersects(msg.source, agent.pos - msg.source))
-                .any(|(t, _)| 0.0 < t && t <= 1.0)
+                .any(|obs| obs.occludes(msg.source, agent.pos))
             {
                 continue;
             }
 
-            if let Some(new_msg) = agent.inform(msg) {
-                self.msg_queue.push_back(new_msg);
+            // Site contact; agents arriving at a busy site queue instead
+            if let Some(site) = self.sites.get_mut(msg.origin).filter(|_| msg.hops == 0) {
+                if site.queues() {
+                    match agent.visit {
+                        Some(Visit::Served { site, .. }) if site == msg.origin => {}
+                        Some(_) => continue,
+                        None if agent.arriving(msg) => {
+                            if site.full() {
+                                agent.visit = Some(Visit::Queued {
+                                    site: msg.origin,
+                                    since: self.time,
+                                });
+                                site.desk.waiting.push_back(idx);
+                                continue;
+                            }
+                            site.desk.serving += 1;
+                            site.desk.admitted += 1;
+                            agent.visit = Some(Visit::Served {
+                                site: msg.origin,
+                                until: self.time + site.service,
+                            });
+                        }
+                        None => {}
+                    }
+                }
+            }
+
+            let arrivals = agent.arrivals;
+            let new_msg = agent.inform(
+                msg,
+                self.time,
+                self.messaging,
+                &self.tour,
+                self.behaviour.as_ref(),
+            );
+            // Only contact with the site itself counts as a visit
+            if msg.hops == 0 && agent.arrivals > arrivals {
+                self.sites[msg.origin].visited += 1;
+            }
+            if let Some(new_msg) = new_msg {
+                if let Some(energy) = &self.energy {
+                    if agent.energy <= 0.0 && !energy.relay_when_empty {
+                        continue;
+                    }
+                    agent.spend(energy.relay_cost);
+                }
+                // Only site contact is direct when agents communicate through the field
+                if let Comm::Direct = self.comm {
+                    self.msg_queue.push_back(new_msg);
+                }
             }
             // for new_msg in agent.inform(msg) {
             //     self.msg_queue.push_back(new_msg);
@@ -0,0 +1,100 @@
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+use super::{field::Field, population::Lost, site::Desk, World};
+use crate::{
+    agent::{Agent, Message},
+    math::Vec2,
+};
+
+/// Everything about a world that changes as it runs, along with the scene it was loaded from;
+/// enough to carry on exactly where it left off.
+#[derive(Deserialize, Serialize)]
+pub struct Checkpoint {
+    pub scene: String,
+    time: f32,
+    rng: ChaCha8Rng,
+    agents: Vec<Agent>,
+    sites: Vec<SiteProgress>,
+    field: Option<Field>,
+    heatmap: Option<Field>,
+    next_chain: u32,
+    spawn_debt: f32,
+    spawned: usize,
+    #[serde(default)]
+    crashed: usize,
+    #[serde(default)]
+    lost: Lost,
+    msg_queue: VecDeque<Message>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct SiteProgress {
+    pos: Vec2,
+    visited: u32,
+    desk: Desk,
+}
+
+impl Checkpoint {
+    /// Copies the running state of a world loaded from `scene`.
+    pub fn of(world: &World, scene: &str) -> Self {
+        Self {
+            scene: scene.to_string(),
+            time: world.time,
+            rng: world.rng.clone(),
+            agents: world.agents.clone(),
+            sites: world
+                .sites
+                .iter()
+                .map(|site| SiteProgress {
+                    pos: site.pos,
+                    visited: site.visited,
+                    desk: site.desk.clone(),
+                })
+                .collect(),
+            field: world.field.clone(),
+            heatmap: world.heatmap.clone(),
+            next_chain: world.next_chain,
+            spawn_debt: world.spawn_debt,
+            spawned: world.spawned,
+            crashed: world.crashed,
+            lost: world.lost.clone(),
+            msg_queue: world.msg_queue.clone(),
+        }
+    }
+
+    /// Puts the saved state into a world freshly loaded from `self.scene`.
+    pub fn restore(self, world: &mut World) {
+        world.time = self.time;
+        world.rng = self.rng;
+        world.agents = self.agents;
+        for (site, progress) in world.sites.iter_mut().zip(self.sites) {
+            site.pos = progress.pos;
+            site.visited = progress.visited;
+            site.desk = progress.desk;
+        }
+        world.field = self.field;
+        world.heatmap = self.heatmap;
+        world.next_chain = self.next_chain;
+        world.spawn_debt = self.spawn_debt;
+        world.spawned = self.spawned;
+        world.crashed = self.crashed;
+        world.lost = self.lost;
+        world.msg_queue = self.msg_queue;
+    }
+
+    /// Writes the checkpoint next to `path` first, so an interrupted save leaves the previous
+    /// one intact.
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let tmp = format!("{path}.tmp");
+        let ron = ron::to_string(self).map_err(|e| format!("serializing checkpoint: {e}"))?;
+        std::fs::write(&tmp, ron).map_err(|e| format!("writing {tmp}: {e}"))?;
+        std::fs::rename(&tmp, path).map_err(|e| format!("replacing {path}: {e}"))
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let ron = std::fs::read_to_string(path).map_err(|e| format!("reading {path}: {e}"))?;
+        ron::from_str(&ron).map_err(|e| format!("parsing {path}: {e}"))
+    }
+}
@@ -0,0 +1,116 @@
+use serde::Deserialize;
+
+use super::{
+    obstacle::{self, Obstacle},
+    spatial::SpatialIndex,
+};
+use crate::{
+    agent::{Agent, Failure},
+    math::Vec2,
+};
+
+/// Agent bodies that push each other apart.
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct Crowding {
+    /// body radius of every agent
+    pub radius: f32,
+    /// separation passes per tick
+    pub iterations: u32,
+    /// regions whose queues are reported
+    pub bottlenecks: Vec<Bottleneck>,
+}
+
+impl Default for Crowding {
+    fn default() -> Self {
+        Self {
+            radius: 0.03,
+            iterations: 2,
+            bottlenecks: Vec::new(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct Bottleneck {
+    pub center: Vec2,
+    pub radius: f32,
+}
+
+impl Bottleneck {
+    /// Agents in the region that are pressed against another agent.
+    pub fn queue(&self, agents: &[Agent]) -> usize {
+        agents
+            .iter()
+            .filter(|agent| agent.contacts > 0)
+            .filter(|agent| (agent.pos - self.center).sq_mag() <= self.radius.powi(2))
+            .count()
+    }
+}
+
+/// Agents that crowding doesn't move.
+fn fixed(agent: &Agent) -> bool {
+    agent.failure == Some(Failure::Crash) || agent.visit.is_some()
+}
+
+/// Moves overlapping agents apart, splitting each overlap between the two unless one has
+/// crashed or is at a site, and so stays put. Corrections that would push an agent into an
+/// obstacle are dropped.
+pub fn separate<'a>(
+    agents: &mut [Agent],
+    crowding: &Crowding,
+    world_size: Vec2,
+    obstacles: impl Iterator<Item = &'a dyn Obstacle> + Clone,
+) {
+    let diameter = 2.0 * crowding.radius;
+    if diameter <= 0.0 {
+        return;
+    }
+    let mut corrections = vec![Vec2::new(0.0, 0.0); agents.len()];
+    let mut contacts = vec![0; agents.len()];
+
+    for pass in 0..crowding.iterations {
+        let index = SpatialIndex::new(world_size, diameter, agents.iter().map(|a| a.pos));
+        corrections
+            .iter_mut()
+            .for_each(|c| *c = Vec2::new(0.0, 0.0));
+
+        for (i, agent) in agents.iter().enumerate() {
+            for j in index.near(agent.pos, diameter).filter(|&j| j != i) {
+                let diff = agent.pos - agents[j].pos;
+                let sq_dist = diff.sq_mag();
+                if sq_dist >= diameter.powi(2) {
+                    continue;
+                }
+                if pass == 0 {
+                    contacts[i] += 1;
+                }
+                if fixed(agent) {
+                    continue;
+                }
+                let dist = sq_dist.sqrt();
+                // Coincident agents are split along an arbitrary but consistent axis
+                let away = if dist > 0.0 {
+                    diff / dist
+                } else if i < j {
+                    Vec2::new(1.0, 0.0)
+                } else {
+                    Vec2::new(-1.0, 0.0)
+                };
+                let share = if fixed(&agents[j]) { 1.0 } else { 0.5 };
+                corrections[i] += share * (diameter - dist) * away;
+            }
+        }
+
+        for (agent, &correction) in agents.iter_mut().zip(&corrections) {
+            let pos = agent.pos + correction;
+            if !obstacle::blocked(obstacles.clone(), pos) {
+                agent.pos = pos;
+            }
+        }
+    }
+
+    for (agent, contacts) in agents.iter_mut().zip(contacts) {
+        agent.contacts = contacts;
+    }
+}
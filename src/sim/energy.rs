@@ -0,0 +1,38 @@
+use serde::Deserialize;
+
+/// Per-agent energy budget.
+#[derive(Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct Energy {
+    pub capacity: f32,
+    /// energy per unit distance moved
+    pub move_cost: f32,
+    /// energy per relayed message
+    pub relay_cost: f32,
+    /// energy regained per second while charging
+    pub recharge: f32,
+    pub recharge_at: Recharge,
+    /// agents without energy still relay messages
+    pub relay_when_empty: bool,
+}
+
+impl Default for Energy {
+    fn default() -> Self {
+        Self {
+            capacity: 100.0,
+            move_cost: 1.0,
+            relay_cost: 0.01,
+            recharge: 20.0,
+            recharge_at: Recharge::Sites,
+            relay_when_empty: false,
+        }
+    }
+}
+
+/// Where agents recharge.
+#[derive(Clone, Copy, Deserialize)]
+pub enum Recharge {
+    Sites,
+    Nest,
+    Both,
+}
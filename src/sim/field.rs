@@ -0,0 +1,184 @@
+use super::{
+    obstacle::{self, Obstacle},
+    render::Renderable,
+};
+use crate::math::Vec2;
+
+use serde::{Deserialize, Serialize};
+
+/// A world-space grid holding one scalar layer per site kind.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct Field {
+    pub cell: f32,
+    pub dims: [usize; 2],
+    /// kind indexed, row-major cells
+    pub layers: Vec<Vec<f32>>,
+    /// cells whose center lies inside an obstacle
+    pub blocked: Vec<bool>,
+}
+
+impl Field {
+    pub fn new<'a>(
+        world_size: Vec2,
+        cell: f32,
+        n_layers: usize,
+        obstacles: impl Iterator<Item = &'a dyn Obstacle> + Clone,
+    ) -> Self {
+        let dims = world_size.map(|v| (v / cell).ceil().max(1.0) as usize);
+        let blocked = (0..dims[0] * dims[1])
+            .map(|idx| {
+                let center = Vec2::new(
+                    ((idx % dims[0]) as f32 + 0.5) * cell,
+                    ((idx / dims[0]) as f32 + 0.5) * cell,
+                );
+                obstacle::blocked(obstacles.clone(), center)
+            })
+            .collect();
+
+        Self {
+            cell,
+            dims,
+            layers: vec![vec![0.0; dims[0] * dims[1]]; n_layers],
+            blocked,
+        }
+    }
+
+    pub fn coord_of(&self, pos: Vec2) -> Option<[usize; 2]> {
+        let coord = pos.map(|v| (v / self.cell).floor());
+        (coord[0] >= 0.0
+            && coord[1] >= 0.0
+            && (coord[0] as usize) < self.dims[0]
+            && (coord[1] as usize) < self.dims[1])
+            .then(|| coord.map(|v| v as usize))
+    }
+
+    pub fn idx_of(&self, pos: Vec2) -> Option<usize> {
+        self.coord_of(pos).map(|[x, y]| y * self.dims[0] + x)
+    }
+
+    pub fn center_of(&self, idx: usize) -> Vec2 {
+        Vec2::new(
+            ((idx % self.dims[0]) as f32 + 0.5) * self.cell,
+            ((idx / self.dims[0]) as f32 + 0.5) * self.cell,
+        )
+    }
+
+    pub fn sample(&self, layer: usize, pos: Vec2) -> f32 {
+        self.idx_of(pos)
+            .map(|idx| self.layers[layer][idx])
+            .unwrap_or(0.0)
+    }
+
+    /// Marks a cell with `value`, keeping whichever of the old and new value is larger.
+    pub fn deposit(&mut self, layer: usize, pos: Vec2, value: f32) {
+        if let Some(idx) = self.idx_of(pos).filter(|&idx| !self.blocked[idx]) {
+            let cell = &mut self.layers[layer][idx];
+            *cell = cell.max(value);
+        }
+    }
+
+    /// Adds `amount` to a cell.
+    pub fn add(&mut self, layer: usize, pos: Vec2, amount: f32) {
+        if let Some(idx) = self.idx_of(pos).filter(|&idx| !self.blocked[idx]) {
+            self.layers[layer][idx] += amount;
+        }
+    }
+
+    pub fn deposit_circle(&mut self, layer: usize, center: Vec2, radius: f32, value: f32) {
+        let radius = radius.max(self.cell / 2.0);
+        let lo = [0, 1].map(|i| ((center[i] - radius) / self.cell).floor().max(0.0) as usize);
+        let hi = [0, 1].map(|i| {
+            (((center[i] + radius) / self.cell).floor().max(0.0) as usize).min(self.dims[i] - 1)
+        });
+        for y in lo[1]..=hi[1] {
+            for x in lo[0]..=hi[0] {
+                let idx = y * self.dims[0] + x;
+                if (self.center_of(idx) - center).sq_mag() <= radius.powi(2) && !self.blocked[idx] {
+                    let cell = &mut self.layers[layer][idx];
+                    *cell = cell.max(value);
+                }
+            }
+        }
+    }
+
+    /// Central-difference gradient of a layer, treating blocked and out-of-world neighbours as
+    /// equal to the sampled cell.
+    pub fn gradient(&self, layer: usize, pos: Vec2) -> Vec2 {
+        let Some([x, y]) = self.coord_of(pos) else {
+            return Vec2::new(0.0, 0.0);
+        };
+        let values = &self.layers[layer];
+        let here = values[y * self.dims[0] + x];
+        let at = |x: Option<usize>, y: Option<usize>| -> f32 {
+            x.zip(y)
+                .filter(|&(x, y)| x < self.dims[0] && y < self.dims[1])
+                .map(|(x, y)| y * self.dims[0] + x)
+                .filter(|&idx| !self.blocked[idx])
+                .map(|idx| values[idx])
+                .unwrap_or(here)
+        };
+        Vec2::new(
+            at(x.checked_add(1), Some(y)) - at(x.checked_sub(1), Some(y)),
+            at(Some(x), y.checked_add(1)) - at(Some(x), y.checked_sub(1)),
+        ) / (2.0 * self.cell)
+    }
+
+    /// Spreads every layer to its open 4-neighbours at `diffusion` per second, then decays it by
+    /// `evaporation` per second.
+    pub fn diffuse(&mut self, delta: f32, diffusion: f32, evaporation: f32) {
+        let rate = (diffusion * delta).min(1.0);
+        let decay = (-evaporation * delta).exp();
+        let [w, h] = self.dims;
+        for layer in &mut self.layers {
+            let old = layer.clone();
+            for y in 0..h {
+                for x in 0..w {
+                    let idx = y * w + x;
+                    if self.blocked[idx] {
+                        continue;
+                    }
+                    let (sum, n) = [
+                        (x > 0).then(|| idx - 1),
+                        (x + 1 < w).then(|| idx + 1),
+                        (y > 0).then(|| idx - w),
+                        (y + 1 < h).then(|| idx + w),
+                    ]
+                    .into_iter()
+                    .flatten()
+                    .filter(|&n| !self.blocked[n])
+                    .fold((0.0, 0), |(sum, n), nbr| (sum + old[nbr], n + 1));
+                    let mean = if n > 0 { sum / n as f32 } else { old[idx] };
+                    layer[idx] = (old[idx] + rate * (mean - old[idx])) * decay;
+                }
+            }
+        }
+    }
+}
+
+impl Renderable for Field {
+    fn render(&self, args: super::render::Args) {
+        let px_per_unit = args.px_per_unit;
+        let px = |v: f32| (v * px_per_unit) as usize;
+        let site_kinds = &args.world.site_kinds;
+        let n_px = px(self.cell).max(1);
+        for idx in 0..self.dims[0] * self.dims[1] {
+            let mut color = [0u8; 3];
+            for (layer, kind_color) in self.layers.iter().zip(site_kinds) {
+                let v = layer[idx].clamp(0.0, 1.0) * 0.5;
+                color
+                    .iter_mut()
+                    .zip(kind_color)
+                    .for_each(|(c, &k)| *c = c.saturating_add((k as f32 * v) as u8));
+            }
+            if color == [0; 3] {
+                continue;
+            }
+            let corner = (self.center_of(idx) - Vec2::new(self.cell, self.cell) / 2.0).map(px);
+            for y in corner[1]..corner[1] + n_px {
+                for x in corner[0]..corner[0] + n_px {
+                    super::render::add_px(args, [x, y], color);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use serde::Deserialize;
+
+use crate::math::Vec2;
+
+/// Wind or current that carries agents along on top of their own motion.
+#[derive(Deserialize)]
+pub struct Flow {
+    pub field: FlowField,
+    /// count the distance moved through the medium rather than over the ground, so going
+    /// upstream costs more than going downstream
+    #[serde(default)]
+    pub asymmetric: bool,
+}
+
+/// Flow velocity as a function of position.
+#[derive(Deserialize)]
+pub enum FlowField {
+    Uniform(Vec2),
+    /// velocities at the points of a grid, bilinearly interpolated between them
+    Grid(FlowGrid),
+    /// rotation about a point, counterclockwise for positive strength
+    Vortex(Vortex),
+    /// flow along x growing linearly with y
+    Shear(Shear),
+}
+
+#[derive(Deserialize)]
+pub struct FlowGrid {
+    /// spacing of the grid points, the first of which is at the origin
+    pub cell: f32,
+    /// `rows[y][x]` is the velocity at `(x * cell, y * cell)`
+    pub rows: Vec<Vec<Vec2>>,
+}
+
+#[derive(Deserialize)]
+pub struct Vortex {
+    pub center: Vec2,
+    /// speed at unit distance from the center; the flow decays with distance past it
+    pub strength: f32,
+}
+
+#[derive(Deserialize)]
+pub struct Shear {
+    /// x velocity at y = 0
+    pub base: f32,
+    /// change of x velocity per unit of y
+    pub rate: f32,
+}
+
+impl FlowField {
+    pub fn velocity(&self, pos: Vec2) -> Vec2 {
+        match self {
+            FlowField::Uniform(vel) => *vel,
+            FlowField::Grid(grid) => grid.sample(pos),
+            FlowField::Vortex(vortex) => {
+                let diff = pos - vortex.center;
+                let sq_dist = diff.sq_mag().max(1.0);
+                vortex.strength / sq_dist * Vec2::new(-diff.y, diff.x)
+            }
+            FlowField::Shear(shear) => Vec2::new(shear.base + shear.rate * pos.y, 0.0),
+        }
+    }
+}
+
+impl FlowGrid {
+    fn sample(&self, pos: Vec2) -> Vec2 {
+        let (Some(n_rows), Some(n_cols)) = (
+            self.rows.len().checked_sub(1),
+            self.rows.first().and_then(|row| row.len().checked_sub(1)),
+        ) else {
+            return Vec2::new(0.0, 0.0);
+        };
+        let [x, y] = [(pos.x, n_cols), (pos.y, n_rows)]
+            .map(|(v, max)| (v / self.cell).clamp(0.0, max as f32));
+        let [x0, y0] = [x, y].map(|v| v.floor() as usize);
+        let [x1, y1] = [(x0, n_cols), (y0, n_rows)].map(|(v, max)| (v + 1).min(max));
+        let [fx, fy] = [x - x0 as f32, y - y0 as f32];
+
+        let at = |x: usize, y: usize| self.rows[y].get(x).copied().unwrap_or(Vec2::new(0.0, 0.0));
+        let lerp = |a: Vec2, b: Vec2, f: f32| (1.0 - f) * a + f * b;
+        lerp(
+            lerp(at(x0, y0), at(x1, y0), fx),
+            lerp(at(x0, y1), at(x1, y1), fx),
+            fy,
+        )
+    }
+}
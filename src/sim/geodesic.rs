@@ -0,0 +1,139 @@
+use std::{cmp::Ordering, collections::BinaryHeap};
+
+use super::{field::Field, site::Site};
+use crate::math::Vec2;
+
+/// Cell side length of the ground truth grid.
+pub const CELL: f32 = 0.05;
+
+/// Grid moves as (dx, dy); knight moves keep the 8-neighbour grid's overestimate of straight
+/// line distances to a few percent.
+const MOVES: [[i32; 2]; 16] = [
+    [1, 0],
+    [0, 1],
+    [-1, 0],
+    [0, -1],
+    [1, 1],
+    [-1, 1],
+    [-1, -1],
+    [1, -1],
+    [2, 1],
+    [1, 2],
+    [-1, 2],
+    [-2, 1],
+    [-2, -1],
+    [-1, -2],
+    [1, -2],
+    [2, -1],
+];
+
+#[derive(PartialEq)]
+struct Entry(f32, usize);
+
+impl Eq for Entry {}
+
+impl PartialOrd for Entry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Entry {
+    // Reversed to make `BinaryHeap` a min-heap
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.0.total_cmp(&self.0).then(other.1.cmp(&self.1))
+    }
+}
+
+/// Cells covered by a site, or the one containing its center if the site is smaller than a cell.
+pub fn site_cells(grid: &Field, site: &Site) -> Vec<usize> {
+    let mut cells: Vec<usize> = (0..grid.blocked.len())
+        .filter(|&idx| !grid.blocked[idx])
+        .filter(|&idx| (grid.center_of(idx) - site.pos).sq_mag() <= site.size.powi(2))
+        .collect();
+    if cells.is_empty() {
+        cells.extend(grid.idx_of(site.pos));
+    }
+    cells
+}
+
+/// Cells reachable from `idx` in one move, with the move's length.
+fn moves(grid: &Field, idx: usize) -> impl Iterator<Item = (usize, f32)> + '_ {
+    let [w, h] = grid.dims.map(|v| v as i32);
+    let free = move |x: i32, y: i32| {
+        x >= 0 && y >= 0 && x < w && y < h && !grid.blocked[(y * w + x) as usize]
+    };
+    let (x, y) = ((idx % grid.dims[0]) as i32, (idx / grid.dims[0]) as i32);
+    MOVES.into_iter().filter_map(move |[dx, dy]| {
+        let (nx, ny) = (x + dx, y + dy);
+        // Every cell the move passes through must be free
+        let clear = free(nx, ny)
+            && match (dx.abs(), dy.abs()) {
+                (1, 1) => free(x + dx, y) && free(x, y + dy),
+                (2, 1) => free(x + dx.signum(), y) && free(x + dx.signum(), y + dy),
+                (1, 2) => free(x, y + dy.signum()) && free(x + dx, y + dy.signum()),
+                _ => true,
+            };
+        clear.then(|| {
+            (
+                (ny * w + nx) as usize,
+                grid.cell * Vec2::new(dx as f32, dy as f32).mag(),
+            )
+        })
+    })
+}
+
+/// Shortest obstacle-avoiding distance from any of `sources` to every cell of `grid`, where a
+/// unit of length in a cell costs `weights[cell]`.
+pub fn distances(grid: &Field, sources: &[usize], weights: &[f32]) -> Vec<f32> {
+    let mut dist = vec![f32::INFINITY; grid.blocked.len()];
+    let mut heap = BinaryHeap::new();
+    for &src in sources {
+        dist[src] = 0.0;
+        heap.push(Entry(0.0, src));
+    }
+
+    while let Some(Entry(d, idx)) = heap.pop() {
+        if d > dist[idx] {
+            continue;
+        }
+        for (nidx, len) in moves(grid, idx) {
+            let weight = (weights[idx] + weights[nidx]) / 2.0;
+            let nd = d + weight * len;
+            if nd < dist[nidx] {
+                dist[nidx] = nd;
+                heap.push(Entry(nd, nidx));
+            }
+        }
+    }
+    dist
+}
+
+/// Cells of a shortest path from the sources of `dist`, as returned by `distances` with the same
+/// `weights`, to `dest`; empty if `dest` can't be reached.
+pub fn path(grid: &Field, dist: &[f32], weights: &[f32], dest: usize) -> Vec<usize> {
+    if !dist[dest].is_finite() {
+        return Vec::new();
+    }
+    let mut cells = vec![dest];
+    let mut idx = dest;
+    while dist[idx] > 0.0 {
+        // Moves are symmetric, so the best way back is a best way here
+        let Some((prev, _)) = moves(grid, idx)
+            .filter(|&(prev, _)| dist[prev] < dist[idx])
+            .map(|(prev, len)| {
+                (
+                    prev,
+                    dist[prev] + (weights[idx] + weights[prev]) / 2.0 * len,
+                )
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+        else {
+            break;
+        };
+        cells.push(prev);
+        idx = prev;
+    }
+    cells.reverse();
+    cells
+}
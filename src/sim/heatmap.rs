@@ -0,0 +1,140 @@
+use std::{fs, io::Write};
+
+use super::{field::Field, geodesic, World};
+use crate::{agent::Failure, math::Vec2};
+
+/// Traffic below this share of the busiest cell makes a cell this many times slower to route
+/// through, so routes stick to where workers actually went.
+const UNUSED_COST: f32 = 100.0;
+
+/// A new heatmap on the ground truth grid, with one layer per pair of kinds workers travel
+/// between.
+pub fn new(world: &World) -> Field {
+    let n_kinds = world.n_kinds();
+    Field::new(
+        world.world_size,
+        geodesic::CELL,
+        n_kinds * n_kinds.saturating_sub(1) / 2,
+        world.obstacles.iter().map(Box::as_ref),
+    )
+}
+
+/// The layer for trips in either direction between kinds `a` and `b` out of `n_kinds`.
+pub fn layer(n_kinds: usize, a: usize, b: usize) -> usize {
+    let (a, b) = (a.min(b), a.max(b));
+    a * n_kinds - a * (a + 1) / 2 + b - a - 1
+}
+
+/// Adds the time each targeting worker spends in its cell to the layer for the trip it's on.
+/// Workers yet to reach a site aren't on a trip between two.
+pub fn accumulate(heatmap: &mut Field, world: &World, delta: f32) {
+    let n_kinds = world.n_kinds();
+    let workers = world
+        .agents
+        .iter()
+        .filter(|a| !a.is_scout && a.failure != Some(Failure::Crash));
+    for agent in workers {
+        if let (Some((to, _)), Some(from)) = (agent.state.target, agent.state.last_site) {
+            if from != to {
+                heatmap.add(layer(n_kinds, from, to), agent.pos, delta);
+            }
+        }
+    }
+}
+
+/// The route workers favour between the nearest sites of two kinds, as cell centers from the
+/// edge of one to the edge of the other.
+pub fn route(heatmap: &Field, world: &World, from: usize, to: usize) -> Option<Vec<Vec2>> {
+    if from == to {
+        return None;
+    }
+    let traffic = heatmap.layers.get(layer(world.n_kinds(), from, to))?;
+    let busiest = traffic.iter().copied().fold(0.0, f32::max);
+    if busiest <= 0.0 {
+        return None;
+    }
+    let weights: Vec<f32> = traffic
+        .iter()
+        .map(|&t| 1.0 / (t / busiest + 1.0 / UNUSED_COST))
+        .collect();
+
+    let cells = |kind: usize| -> Vec<usize> {
+        world
+            .sites
+            .iter()
+            .filter(|site| site.kind == kind && site.present(world.time))
+            .flat_map(|site| geodesic::site_cells(heatmap, site))
+            .collect()
+    };
+    let dist = geodesic::distances(heatmap, &cells(from), &weights);
+    let dest = cells(to)
+        .into_iter()
+        .min_by(|&a, &b| dist[a].total_cmp(&dist[b]))?;
+    let path = geodesic::path(heatmap, &dist, &weights, dest);
+    (!path.is_empty()).then(|| path.into_iter().map(|idx| heatmap.center_of(idx)).collect())
+}
+
+pub fn length(route: &[Vec2]) -> f32 {
+    route.windows(2).map(|seg| (seg[1] - seg[0]).mag()).sum()
+}
+
+/// Writes the heatmap as `{prefix}.ppm`, with routes drawn over it, and the routes between every
+/// pair of kinds as `{prefix}.csv`. -> (from, to, route length, ground truth length)
+pub fn export(
+    heatmap: &Field,
+    world: &World,
+    prefix: &str,
+) -> std::io::Result<Vec<(usize, usize, f32, f32)>> {
+    let n_kinds = world.n_kinds();
+    let optimal = world.kind_distances();
+    let routes: Vec<(usize, usize, Vec<Vec2>)> = (0..n_kinds)
+        .flat_map(|from| (from + 1..n_kinds).map(move |to| (from, to)))
+        .filter_map(|(from, to)| Some((from, to, route(heatmap, world, from, to)?)))
+        .collect();
+
+    let mut csv = String::from("from,to,x,y\n");
+    for (from, to, points) in &routes {
+        for point in points {
+            csv += &format!("{from},{to},{},{}\n", point.x, point.y);
+        }
+    }
+    fs::write(format!("{prefix}.csv"), csv)?;
+
+    // Log scaled so quiet side routes still show
+    let total: Vec<f32> = (0..heatmap.blocked.len())
+        .map(|idx| heatmap.layers.iter().map(|layer| layer[idx]).sum())
+        .collect();
+    let busiest = total.iter().copied().fold(0.0, f32::max);
+    let mut pixels: Vec<[u8; 3]> = total
+        .iter()
+        .zip(&heatmap.blocked)
+        .map(|(&t, &blocked)| {
+            if blocked {
+                return [0x85, 0x53, 0x09];
+            }
+            let v = (1.0 + t).ln() / (1.0 + busiest).ln().max(f32::EPSILON);
+            [v, v * v, v.powi(4)].map(|c| (c * 255.0) as u8)
+        })
+        .collect();
+    for (_, _, points) in &routes {
+        for &point in points {
+            if let Some(idx) = heatmap.idx_of(point) {
+                pixels[idx] = [0xff; 3];
+            }
+        }
+    }
+    for site in world.sites.iter().filter(|site| site.present(world.time)) {
+        for idx in geodesic::site_cells(heatmap, site) {
+            pixels[idx] = world.site_kinds[site.kind];
+        }
+    }
+
+    let mut ppm = fs::File::create(format!("{prefix}.ppm"))?;
+    write!(ppm, "P6\n{} {}\n255\n", heatmap.dims[0], heatmap.dims[1])?;
+    ppm.write_all(&pixels.concat())?;
+
+    Ok(routes
+        .iter()
+        .map(|(from, to, points)| (*from, *to, length(points), optimal[*from][*to]))
+        .collect())
+}
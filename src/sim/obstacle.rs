@@ -1,15 +1,147 @@
 use serde::Deserialize;
-use std::ops::Range;
+use std::{
+    fmt,
+    ops::{Deref, DerefMut, Range},
+};
 
 use super::render::Renderable;
 use crate::math::Vec2;
 
+/// Distance within which a point counts as on an obstacle's surface. Bounces restart this far
+/// off the surface, so nothing comes to rest on the boundary where it would count as inside, and
+/// queries ignore crossings this close behind or ahead of where they start.
+pub const SURFACE_GAP: f32 = 1e-4;
+
+/// Where a ray crosses an obstacle's boundary. Both values are always finite.
+#[derive(Clone, Copy, Debug)]
+pub struct Hit {
+    t: f32,
+    normal: Vec2,
+}
+
+impl Hit {
+    /// -> None unless both values are finite, as they aren't for a degenerate shape
+    pub fn new(t: f32, normal: Vec2) -> Option<Self> {
+        (t.is_finite() && normal.x.is_finite() && normal.y.is_finite())
+            .then_some(Self { t, normal })
+    }
+
+    /// Multiple of the ray at which it crosses.
+    pub fn t(&self) -> f32 {
+        self.t
+    }
+
+    /// Unit normal pointing out of the obstacle.
+    pub fn normal(&self) -> Vec2 {
+        self.normal
+    }
+}
+
+/// Most crossings a line makes with a convex shape: two points, each of which may be a corner
+/// shared by two edges.
+const MAX_HITS: usize = 4;
+
+/// The crossings from one `Obstacle::intersects` query, held inline so that no query allocates.
+#[derive(Clone, Copy)]
+pub struct Hits {
+    hits: [Hit; MAX_HITS],
+    len: usize,
+}
+
+impl Hits {
+    /// Adds a crossing. Panics beyond the most a convex shape can have; scenes are checked for
+    /// shapes that aren't when they load.
+    pub fn push(&mut self, hit: Hit) {
+        assert!(
+            self.len < MAX_HITS,
+            "more crossings than a convex shape has"
+        );
+        self.hits[self.len] = hit;
+        self.len += 1;
+    }
+}
+
+impl Default for Hits {
+    fn default() -> Self {
+        let hit = Hit {
+            t: 0.0,
+            normal: Vec2::new(0.0, 0.0),
+        };
+        Self {
+            hits: [hit; MAX_HITS],
+            len: 0,
+        }
+    }
+}
+
+impl fmt::Debug for Hits {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl Deref for Hits {
+    type Target = [Hit];
+
+    fn deref(&self) -> &[Hit] {
+        &self.hits[..self.len]
+    }
+}
+
+impl DerefMut for Hits {
+    fn deref_mut(&mut self) -> &mut [Hit] {
+        &mut self.hits[..self.len]
+    }
+}
+
+impl FromIterator<Hit> for Hits {
+    fn from_iter<I: IntoIterator<Item = Hit>>(iter: I) -> Self {
+        let mut hits = Self::default();
+        iter.into_iter().for_each(|hit| hits.push(hit));
+        hits
+    }
+}
+
+impl IntoIterator for Hits {
+    type Item = Hit;
+    type IntoIter = std::iter::Take<std::array::IntoIter<Hit, MAX_HITS>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.hits.into_iter().take(self.len)
+    }
+}
+
+impl<'a> IntoIterator for &'a Hits {
+    type Item = &'a Hit;
+    type IntoIter = std::slice::Iter<'a, Hit>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
 pub trait Obstacle {
     fn bounding_box(&self) -> [Range<f32>; 2];
-    /// Assuming a point is *inside the bounding box*, is it in the shape?
+    /// Assuming a point is *inside the bounding box*, is it in the shape? Points on the boundary
+    /// aren't.
     fn inside(&self, coord: Vec2) -> bool;
-    /// -> (ray multiplier, normal vector)
-    fn intersects(&self, origin: Vec2, ray: Vec2) -> Vec<(f32, Vec2)>;
+    /// Every crossing of the boundary by the line through `origin` along `ray`, in no particular
+    /// order; nothing for a zero `ray`.
+    fn intersects(&self, origin: Vec2, ray: Vec2) -> Hits;
+
+    /// The first crossing ahead of `origin` along `ray`, no further than `max_t` rays.
+    fn nearest_hit(&self, origin: Vec2, ray: Vec2, max_t: f32) -> Option<Hit> {
+        let min_t = SURFACE_GAP / ray.mag();
+        self.intersects(origin, ray)
+            .into_iter()
+            .filter(|hit| min_t < hit.t && hit.t <= max_t)
+            .min_by(|a, b| a.t.total_cmp(&b.t))
+    }
+
+    /// Does the shape stand between two points?
+    fn occludes(&self, from: Vec2, to: Vec2) -> bool {
+        self.nearest_hit(from, to - from, 1.0).is_some()
+    }
 
     /// -> (hit pos, delta)
     fn process_collision(&self, origin: Vec2, delta: Vec2) -> Option<(Vec2, Vec2)> {
@@ -19,42 +151,52 @@ pub trait Obstacle {
             return None;
         }
 
-        // eprintln!("collision");
-
-        let hits = self.intersects(origin, delta);
-        let Some((t, norm)) = hits
+        // The end is inside, so the last crossing before it is where the shape was entered;
+        // earlier ones, such as walls behind an agent in an `InvRect`, don't matter
+        let min_t = -SURFACE_GAP / delta.mag();
+        let hit = self
+            .intersects(origin, delta)
             .into_iter()
-            .filter(|&(t, _)| -1.0 < t && t <= 1.0)
-            .min_by(|(a, _), (b, _)| a.total_cmp(b))
-        else {
-            // eprintln!("oops");
-            return None;
-        };
+            .filter(|hit| min_t <= hit.t && hit.t <= 1.0)
+            .max_by(|a, b| a.t.total_cmp(&b.t))?;
 
-        let hit_pos = origin + t * delta;
-        let rest_delta = (1.0 - t) * delta;
-        let refl_delta = rest_delta - 2.0 * norm * rest_delta.dot(norm);
+        let hit_pos = origin + hit.t * delta + SURFACE_GAP * hit.normal;
+        let rest_delta = (1.0 - hit.t) * delta;
+        let refl_delta = rest_delta - 2.0 * hit.normal * rest_delta.dot(hit.normal);
 
         Some((hit_pos, refl_delta))
     }
 }
 
+/// Is a point inside any of the obstacles?
+pub fn blocked<'a>(mut obstacles: impl Iterator<Item = &'a dyn Obstacle>, coord: Vec2) -> bool {
+    obstacles.any(|obs| {
+        let bbox = obs.bounding_box();
+        [0, 1].map(|i| bbox[i].contains(&coord[i])) == [true; 2] && obs.inside(coord)
+    })
+}
+
 impl Renderable for Box<dyn Obstacle> {
     fn render(&self, args: super::render::Args) {
-        let px_per_unit = args.px_per_unit;
-        let px = |v| (v * px_per_unit) as usize;
-        let unpx = |v| v as f32 / px_per_unit;
+        fill(args, self.as_ref(), [0x85, 0x53, 0x09]);
+    }
+}
 
-        let bbox = self.bounding_box();
-        // let [x_range, y_range] = self.bounding_box().map(|r| px(r.start)..=px(r.end));
-        let [x_range, y_range] = [0, 1]
-            .map(|i| px(bbox[i].start.max(0.0))..=px(bbox[i].end.min(args.world.world_size[i])));
-        let bbox_iter = x_range.flat_map(|x| y_range.clone().map(move |y| [x, y]));
-
-        for px_coord in bbox_iter {
-            if self.inside(px_coord.map(unpx).into()) {
-                super::render::put_px(args, px_coord, [0x85, 0x53, 0x09]);
-            }
+/// Colors every pixel inside a shape.
+pub fn fill(args: super::render::Args, shape: &dyn Obstacle, color: [u8; 3]) {
+    let px_per_unit = args.px_per_unit;
+    let px = |v| (v * px_per_unit) as usize;
+    let unpx = |v| v as f32 / px_per_unit;
+
+    let bbox = shape.bounding_box();
+    // let [x_range, y_range] = self.bounding_box().map(|r| px(r.start)..=px(r.end));
+    let [x_range, y_range] =
+        [0, 1].map(|i| px(bbox[i].start.max(0.0))..=px(bbox[i].end.min(args.world.world_size[i])));
+    let bbox_iter = x_range.flat_map(|x| y_range.clone().map(move |y| [x, y]));
+
+    for px_coord in bbox_iter {
+        if shape.inside(px_coord.map(unpx).into()) {
+            super::render::put_px(args, px_coord, color);
         }
     }
 }
@@ -81,35 +223,42 @@ impl Obstacle for Circle {
         (coord - self.center).sq_mag() < self.radius.powi(2)
     }
 
-    fn intersects(&self, origin: Vec2, ray: Vec2) -> Vec<(f32, Vec2)> {
+    fn intersects(&self, origin: Vec2, ray: Vec2) -> Hits {
         let sq_r = self.radius.powi(2);
         let diff = self.center - origin;
         let diff_sq_mag = diff.sq_mag();
         let v_sq_mag = ray.sq_mag();
         let v_dot_diff = ray.dot(diff);
 
-        let const_term = v_dot_diff / v_sq_mag;
-        let pm_term = (v_dot_diff.powi(2) - v_sq_mag * (diff_sq_mag - sq_r)).sqrt() / v_sq_mag;
+        let discriminant = v_dot_diff.powi(2) - v_sq_mag * (diff_sq_mag - sq_r);
+        if v_sq_mag == 0.0 || discriminant < 0.0 {
+            return Hits::default();
+        }
 
-        let ts = [-1.0, 1.0].map(|v| const_term + v * pm_term);
-        let norms = ts
-            .map(|t| origin + t * ray)
-            .map(|hit| (hit - self.center).norm());
+        let const_term = v_dot_diff / v_sq_mag;
+        let pm_term = discriminant.sqrt() / v_sq_mag;
 
-        [0, 1].map(|i| (ts[i], norms[i])).into()
+        [-1.0, 1.0]
+            .into_iter()
+            .map(|v| const_term + v * pm_term)
+            .filter_map(|t| Hit::new(t, (origin + t * ray - self.center).norm()))
+            .collect()
     }
 }
 
 // Vertices must be supplied in right-handedly counterclockwise order.
 #[derive(Deserialize)]
-#[serde(from = "[Vec2; 3]")]
+#[serde(try_from = "[Vec2; 3]")]
 pub struct Triangle {
     pub verts: [Vec2; 3],
 }
 
-impl From<[Vec2; 3]> for Triangle {
-    fn from(verts: [Vec2; 3]) -> Self {
-        Self { verts }
+impl TryFrom<[Vec2; 3]> for Triangle {
+    type Error = String;
+
+    fn try_from(verts: [Vec2; 3]) -> Result<Self, String> {
+        check_convex(&verts)?;
+        Ok(Self { verts })
     }
 }
 
@@ -125,40 +274,119 @@ impl Obstacle for Triangle {
     }
 
     fn inside(&self, coord: Vec2) -> bool {
-        let abv_line = |right: Vec2, left: Vec2| -> bool {
-            (right - coord).cross(left - coord).is_sign_negative()
-        };
+        convex_inside(&self.verts, coord)
+    }
 
-        let mut shifted_verts = self.verts;
-        shifted_verts.rotate_right(1);
-        self.verts
-            .into_iter()
-            .zip(shifted_verts)
-            .all(|(right, left)| abv_line(right, left))
-    }
-
-    fn intersects(&self, origin: Vec2, ray: Vec2) -> Vec<(f32, Vec2)> {
-        let line_int = |p1: Vec2, p2: Vec2| -> Option<(f32, Vec2)> {
-            let diff = p2 - p1;
-            let coefs = Vec2::new(-diff.y, diff.x);
-            Some(-(coefs.dot(origin) + p1.cross(p2)) / coefs.dot(ray))
-                .filter(|&t| {
-                    let end = origin + t * ray;
-                    (p1 - end).dot(p2 - end).is_sign_negative()
-                })
-                .map(|t| (t, -coefs.norm()))
-        };
+    fn intersects(&self, origin: Vec2, ray: Vec2) -> Hits {
+        convex_intersects(&self.verts, origin, ray)
+    }
+}
 
-        let mut shifted_verts = self.verts;
-        shifted_verts.rotate_right(1);
-        self.verts
-            .into_iter()
-            .zip(shifted_verts)
-            .filter_map(|(p2, p1)| line_int(p1, p2))
-            .collect()
+// Vertices must be supplied in right-handedly counterclockwise order, and the polygon must be
+// convex.
+#[derive(Deserialize)]
+#[serde(try_from = "Vec<Vec2>")]
+pub struct Polygon {
+    pub verts: Vec<Vec2>,
+}
+
+impl TryFrom<Vec<Vec2>> for Polygon {
+    type Error = String;
+
+    fn try_from(verts: Vec<Vec2>) -> Result<Self, String> {
+        check_convex(&verts)?;
+        Ok(Self { verts })
+    }
+}
+
+/// Checks that the corners go once around counterclockwise, turning left or going straight on
+/// at each, which the inside and intersection tests rely on.
+fn check_convex(verts: &[Vec2]) -> Result<(), String> {
+    if verts.len() < 3 {
+        return Err(format!(
+            "a shape needs at least 3 corners, not {}",
+            verts.len()
+        ));
+    }
+    let mut turned = 0.0;
+    let n = verts.len();
+    for i in 0..n {
+        let (prev, corner, next) = (verts[(i + n - 1) % n], verts[i], verts[(i + 1) % n]);
+        let (along, then) = (corner - prev, next - corner);
+        let turn = along.cross(then).atan2(along.dot(then));
+        if turn < 0.0 {
+            return Err(format!(
+                "corners {verts:?} aren't convex and counterclockwise"
+            ));
+        }
+        turned += turn;
+    }
+    if (turned - std::f32::consts::TAU).abs() > 1e-3 {
+        return Err(format!(
+            "corners {verts:?} go round {turned} radians, not once"
+        ));
+    }
+    Ok(())
+}
+
+impl Obstacle for Polygon {
+    fn bounding_box(&self) -> [Range<f32>; 2] {
+        [0, 1].map(|i| {
+            let vs = self.verts.iter().map(|vert| vert[i]);
+            vs.clone().min_by(f32::total_cmp).unwrap_or(0.0)
+                ..vs.max_by(f32::total_cmp).unwrap_or(0.0)
+        })
+    }
+
+    fn inside(&self, coord: Vec2) -> bool {
+        convex_inside(&self.verts, coord)
+    }
+
+    fn intersects(&self, origin: Vec2, ray: Vec2) -> Hits {
+        convex_intersects(&self.verts, origin, ray)
     }
 }
 
+/// Each vertex paired with the one before it.
+fn edges(verts: &[Vec2]) -> impl Iterator<Item = (Vec2, Vec2)> + '_ {
+    verts.iter().copied().zip(
+        verts
+            .iter()
+            .copied()
+            .cycle()
+            .skip(verts.len().saturating_sub(1)),
+    )
+}
+
+fn convex_inside(verts: &[Vec2], coord: Vec2) -> bool {
+    let abv_line = |right: Vec2, left: Vec2| -> bool { (right - coord).cross(left - coord) < 0.0 };
+
+    edges(verts).all(|(right, left)| abv_line(right, left))
+}
+
+fn convex_intersects(verts: &[Vec2], origin: Vec2, ray: Vec2) -> Hits {
+    let line_int = |p1: Vec2, p2: Vec2| -> Option<Hit> {
+        let diff = p2 - p1;
+        let coefs = Vec2::new(-diff.y, diff.x);
+        // Parallel to the edge
+        let along = coefs.dot(ray);
+        if along == 0.0 {
+            return None;
+        }
+        Some(-(coefs.dot(origin) + p1.cross(p2)) / along)
+            .filter(|&t| {
+                // Between the edge's ends
+                let end = origin + t * ray;
+                (p1 - end).dot(p2 - end) <= 0.0
+            })
+            .and_then(|t| Hit::new(t, -coefs.norm()))
+    };
+
+    edges(verts)
+        .filter_map(|(p2, p1)| line_int(p1, p2))
+        .collect()
+}
+
 // Corners must be supplied as (x min, y min), (x max, y max)
 #[derive(Deserialize)]
 #[serde(from = "[Range<f32>; 2]")]
@@ -177,27 +405,34 @@ impl Obstacle for Rect {
         self.ranges.clone()
     }
 
-    fn inside(&self, _coord: Vec2) -> bool {
-        true
+    fn inside(&self, coord: Vec2) -> bool {
+        [0, 1].into_iter().all(|i| {
+            let range = &self.ranges[i];
+            range.start < coord[i] && coord[i] < range.end
+        })
     }
 
-    fn intersects(&self, origin: Vec2, ray: Vec2) -> Vec<(f32, Vec2)> {
+    fn intersects(&self, origin: Vec2, ray: Vec2) -> Hits {
         [0, 1]
             .into_iter()
+            // A ray along one axis never meets the walls across it
+            .filter(|&axis| ray[axis] != 0.0)
             .flat_map(|axis| {
                 [0, 1]
                     .map(|extr| {
                         let mut norm = [extr as f32 * 2.0 - 1.0, 0.0];
                         norm.rotate_right(axis);
                         let range = &self.ranges[axis];
-                        (
+                        Hit::new(
                             ([range.start, range.end][extr] - origin[axis]) / ray[axis],
                             norm.into(),
                         )
                     })
                     .into_iter()
-                    .filter(move |&(t, _)| {
-                        self.ranges[1 - axis].contains(&(origin + t * ray)[1 - axis])
+                    .flatten()
+                    .filter(move |hit| {
+                        let range = &self.ranges[1 - axis];
+                        (range.start..=range.end).contains(&(origin + hit.t * ray)[1 - axis])
                     })
             })
             .collect()
@@ -212,38 +447,51 @@ impl Obstacle for InvRect {
 
     fn inside(&self, coord: Vec2) -> bool {
         let bbox = self.0.bounding_box();
-        [0, 1].map(|i| bbox[i].contains(&coord[i])) != [true, true]
+        [0, 1]
+            .into_iter()
+            .any(|i| !(bbox[i].start..=bbox[i].end).contains(&coord[i]))
     }
 
-    fn intersects(&self, origin: Vec2, ray: Vec2) -> Vec<(f32, Vec2)> {
+    fn intersects(&self, origin: Vec2, ray: Vec2) -> Hits {
         let mut ints = self.0.intersects(origin, ray);
-        ints.iter_mut().for_each(|(_, norm)| *norm *= -1.0);
+        ints.iter_mut().for_each(|hit| hit.normal *= -1.0);
         ints
     }
 }
 
-pub fn deser_obstacles<'de, D>(d: D) -> Result<Vec<Box<dyn Obstacle>>, D::Error>
-where
-    D: serde::Deserializer<'de>,
-{
-    macro_rules! gen_obs_deser {
+/// Any obstacle shape; each is tried in turn, so the shape's name in a scene is only for the
+/// reader.
+pub struct Shape(pub Box<dyn Obstacle>);
+
+impl<'de> Deserialize<'de> for Shape {
+    fn deserialize<D>(d: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        macro_rules! gen_obs_deser {
             ($($obs:ident),*) => {
                 #[derive(Deserialize)]
                 #[serde(untagged)]
                 enum ObsObj { $($obs($obs)),* }
 
-                let obj_vec: Vec<ObsObj> = Deserialize::deserialize(d)?;
+                let obj: ObsObj = Deserialize::deserialize(d)?;
 
-                return Ok(obj_vec
-                    .into_iter()
-                    .map(|o| match o {
-                        $(
-                            ObsObj::$obs(v) => Box::new(v) as Box<dyn Obstacle>,
-                        )*
-                    })
-                    .collect());
+                return Ok(Shape(match obj {
+                    $(
+                        ObsObj::$obs(v) => Box::new(v) as Box<dyn Obstacle>,
+                    )*
+                }));
             };
         }
 
-    gen_obs_deser!(Circle, Triangle, Rect);
+        gen_obs_deser!(Circle, Triangle, Rect, Polygon);
+    }
+}
+
+pub fn deser_obstacles<'de, D>(d: D) -> Result<Vec<Box<dyn Obstacle>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let shapes: Vec<Shape> = Deserialize::deserialize(d)?;
+    Ok(shapes.into_iter().map(|shape| shape.0).collect())
 }
@@ -0,0 +1,85 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    agent::{Agent, Failure, Routes},
+    math::Vec2,
+};
+
+/// Agent births and failures.
+#[derive(Default, Deserialize)]
+#[serde(default)]
+pub struct Population {
+    /// agents spawned per second at the nest
+    pub spawn_rate: f32,
+    /// spawning pauses while at least this many agents are working
+    pub max_agents: Option<usize>,
+    /// chance per second of each working agent failing
+    pub failure_rate: f32,
+    /// share of random failures that leave the radio stuck rather than crash the agent
+    pub radio_share: f32,
+    pub mass_failures: Vec<MassFailure>,
+}
+
+/// Failure of agents in a region at a scheduled time.
+#[derive(Deserialize)]
+pub struct MassFailure {
+    pub time: f32,
+    pub center: Vec2,
+    pub radius: f32,
+    /// share of the agents in the region that fail
+    pub fraction: f32,
+    pub failure: Failure,
+}
+
+/// Agent counts by condition.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Census {
+    pub working: usize,
+    /// agents crashed since the start, whether or not their wrecks are still around
+    pub crashed: usize,
+    pub mute: usize,
+    /// agents added after the start
+    pub spawned: usize,
+}
+
+/// What agents made before their slots went to new agents, so the swarm's metrics keep it.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct Lost {
+    pub shortest_dist: f32,
+    pub best_tour: f32,
+    /// best trips and trip counts; `latest` stays unset, as it describes the agents still around
+    pub routes: Routes,
+    pub energy_spent: f32,
+}
+
+impl Default for Lost {
+    fn default() -> Self {
+        Self {
+            shortest_dist: f32::MAX,
+            best_tour: f32::MAX,
+            routes: Routes::default(),
+            energy_spent: 0.0,
+        }
+    }
+}
+
+impl Lost {
+    /// Keeps what `agent` made before it goes.
+    pub fn add(&mut self, agent: &Agent) {
+        self.shortest_dist = self.shortest_dist.min(agent.shortest_dist);
+        self.best_tour = self.best_tour.min(agent.best_tour);
+        self.energy_spent += agent.energy_spent;
+        let n_kinds = agent.routes.n_kinds;
+        // The world's kinds aren't known until the first agent goes
+        if self.routes.best.is_empty() {
+            self.routes = Routes::new(n_kinds);
+        }
+        if self.routes.n_kinds != n_kinds {
+            return;
+        }
+        for idx in 0..n_kinds * n_kinds {
+            self.routes.best[idx] = self.routes.best[idx].min(agent.routes.best[idx]);
+            self.routes.trips[idx] += agent.routes.trips[idx];
+        }
+    }
+}
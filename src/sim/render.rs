@@ -56,8 +56,7 @@ pub fn draw_circle(args: Args, center: Vec2, radius: f32, color: [u8; 3]) {
         let sq_dist: usize = diff.map(|x| x.pow(2)).into_iter().sum();
 
         if sq_dist <= sq_size {
-            let idx = 4 * (coord[1] * args.px_width + coord[0]);
-            args.frame[idx..idx + 3].copy_from_slice(&color);
+            put_px(args, coord, color);
         }
     }
 }
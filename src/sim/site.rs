@@ -1,32 +1,242 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::VecDeque, f32::consts::PI};
+
 use crate::{agent::Message, math::Vec2};
 
-#[derive(serde::Deserialize)]
+#[derive(Deserialize)]
 pub struct Site {
+    /// current position; overwritten every update by `motion` unless static
     pub pos: Vec2,
     pub kind: usize,
     pub size: f32,
+    #[serde(default)]
+    pub motion: Motion,
+    /// `(from, until)` times the site exists; always when empty
+    #[serde(default)]
+    pub active: Vec<(f32, f32)>,
+    /// arrivals after which the site is used up and vanishes
+    #[serde(default)]
+    pub visits: Option<u32>,
+    #[serde(skip)]
+    pub visited: u32,
+    /// seconds an arriving agent spends at the site before it can leave
+    #[serde(default)]
+    pub service: f32,
+    /// agents served at once; others queue
+    #[serde(default)]
+    pub capacity: Option<u32>,
+    #[serde(skip)]
+    pub desk: Desk,
+}
+
+/// Agents being served or waiting at a site, and how the site has coped so far.
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct Desk {
+    pub serving: u32,
+    /// indices into `World::agents`, first come first served
+    pub waiting: VecDeque<usize>,
+    /// agents that started service
+    pub admitted: u32,
+    /// agents that finished service
+    pub served: u32,
+    /// total time admitted agents spent queueing
+    pub waited: f32,
+}
+
+impl Desk {
+    /// Mean time admitted agents spent queueing; 0 until someone is admitted.
+    pub fn mean_wait(&self) -> f32 {
+        if self.admitted == 0 {
+            return 0.0;
+        }
+        self.waited / self.admitted as f32
+    }
+}
+
+/// Scripted site movement as a function of time.
+#[derive(Default, Deserialize)]
+pub enum Motion {
+    #[default]
+    Static,
+    Orbit(Orbit),
+    Path(Path),
+}
+
+#[derive(Deserialize)]
+pub struct Orbit {
+    pub center: Vec2,
+    pub radius: f32,
+    /// seconds per counterclockwise revolution
+    pub period: f32,
+    /// angle at time zero
+    #[serde(default)]
+    pub phase: f32,
+}
+
+/// Constant-speed travel along straight segments through `points`.
+#[derive(Deserialize)]
+#[serde(from = "PathPoints")]
+pub struct Path {
+    pub points: Vec<Vec2>,
+    pub speed: f32,
+    /// return to the first point after the last one instead of turning back along the path
+    pub closed: bool,
+    /// `(start, end, length)` of each segment travelled in a round, leaving out those of no
+    /// length
+    segments: Vec<(Vec2, Vec2, f32)>,
+    length: f32,
+}
+
+#[derive(Deserialize)]
+struct PathPoints {
+    points: Vec<Vec2>,
+    speed: f32,
+    #[serde(default)]
+    closed: bool,
+}
+
+impl From<PathPoints> for Path {
+    fn from(path: PathPoints) -> Self {
+        let PathPoints {
+            points,
+            speed,
+            closed,
+        } = path;
+        let mut round = points.clone();
+        if closed {
+            round.extend(points.first());
+        } else {
+            round.extend(points.iter().rev().skip(1));
+        }
+        let segments: Vec<_> = round
+            .windows(2)
+            .map(|seg| (seg[0], seg[1], (seg[1] - seg[0]).mag()))
+            .filter(|&(_, _, len)| len > 0.0)
+            .collect();
+        let length = segments.iter().map(|&(_, _, len)| len).sum();
+        Self {
+            points,
+            speed,
+            closed,
+            segments,
+            length,
+        }
+    }
+}
+
+impl Path {
+    fn pos_at(&self, time: f32) -> Option<Vec2> {
+        if self.length <= 0.0 {
+            return self.points.first().copied();
+        }
+
+        let mut along = (self.speed * time).rem_euclid(self.length);
+        for &(a, b, seg_len) in &self.segments {
+            if along <= seg_len {
+                return Some(a + along / seg_len * (b - a));
+            }
+            along -= seg_len;
+        }
+        self.segments.last().map(|&(_, b, _)| b)
+    }
+}
+
+impl Motion {
+    /// Checks for motion that has no position at some times.
+    pub fn check(&self) -> Result<(), String> {
+        match self {
+            Motion::Orbit(orbit) if orbit.period <= 0.0 || orbit.period.is_nan() => Err(format!(
+                "an orbit's period must be positive, not {}",
+                orbit.period
+            )),
+            _ => Ok(()),
+        }
+    }
 }
 
 impl Site {
-    pub fn collision_msg(&self) -> Message {
+    /// Moves the site to where its motion puts it at `time`.
+    pub fn update(&mut self, time: f32) {
+        match &self.motion {
+            Motion::Static => {}
+            Motion::Orbit(orbit) => {
+                let angle = orbit.phase + 2.0 * PI * time / orbit.period;
+                self.pos = orbit.center + orbit.radius * Vec2::new(angle.cos(), angle.sin());
+            }
+            Motion::Path(path) => {
+                if let Some(pos) = path.pos_at(time) {
+                    self.pos = pos;
+                }
+            }
+        }
+    }
+
+    /// Whether arriving agents are held at the site.
+    pub fn queues(&self) -> bool {
+        self.service > 0.0 || self.capacity.is_some()
+    }
+
+    pub fn full(&self) -> bool {
+        self.capacity.is_some_and(|cap| self.desk.serving >= cap)
+    }
+
+    /// Whether the site is on its timeline and not used up.
+    pub fn present(&self, time: f32) -> bool {
+        (self.active.is_empty()
+            || self
+                .active
+                .iter()
+                .any(|&(from, until)| (from..until).contains(&time)))
+            && self.visits.is_none_or(|visits| self.visited < visits)
+    }
+
+    pub fn collision_msg(&self, id: usize, time: f32) -> Message {
         Message {
             site_kind: self.kind,
             sq_dist: 0.0,
             range: self.size,
             source: self.pos,
+            hops: 0,
+            origin: id,
+            time,
+            chain: None,
         }
     }
 
-    pub fn visibility_msg(&self) -> Message {
+    #[allow(dead_code)]
+    pub fn visibility_msg(&self, id: usize, time: f32) -> Message {
         Message {
             site_kind: self.kind,
             sq_dist: 0.8,
             range: 0.8,
             source: self.pos,
+            hops: 0,
+            origin: id,
+            time,
+            chain: None,
         }
     }
 }
 
+/// Home area of the swarm.
+#[derive(serde::Deserialize)]
+pub struct Nest {
+    pub pos: Vec2,
+    pub size: f32,
+}
+
+impl Nest {
+    pub fn contains(&self, pos: Vec2) -> bool {
+        (pos - self.pos).sq_mag() <= self.size.powi(2)
+    }
+}
+
+impl super::render::Renderable for Nest {
+    fn render(&self, args: super::render::Args) {
+        super::render::draw_circle(args, self.pos, self.size, [0x3a, 0x3b, 0x4e]);
+    }
+}
+
 impl super::render::Renderable for Site {
     fn render(&self, args: super::render::Args) {
         super::render::draw_circle(
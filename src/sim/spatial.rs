@@ -0,0 +1,63 @@
+use crate::math::Vec2;
+
+/// Uniform grid over the world bucketing item indices by position. Positions outside the world
+/// are clamped into the edge cells.
+pub struct SpatialIndex {
+    cell: f32,
+    dims: [usize; 2],
+    /// `items[starts[c]..starts[c + 1]]` are the items in cell `c`
+    starts: Vec<usize>,
+    items: Vec<usize>,
+}
+
+impl SpatialIndex {
+    /// Cells are `cell` wide, or wider where that would make more cells than items, so the grid
+    /// stays as small as the items however small `cell` is.
+    pub fn new(world_size: Vec2, cell: f32, positions: impl Iterator<Item = Vec2>) -> Self {
+        let positions: Vec<Vec2> = positions.collect();
+        let cell = cell.max((world_size.x * world_size.y / positions.len().max(1) as f32).sqrt());
+        let dims = world_size.map(|v| (v / cell).ceil().max(1.0) as usize);
+        let mut index = Self {
+            cell,
+            dims,
+            starts: vec![0; dims[0] * dims[1] + 1],
+            items: Vec::new(),
+        };
+
+        // Counting sort by cell
+        let cells: Vec<usize> = positions.iter().map(|&pos| index.cell_of(pos)).collect();
+        for &c in &cells {
+            index.starts[c + 1] += 1;
+        }
+        for c in 0..dims[0] * dims[1] {
+            index.starts[c + 1] += index.starts[c];
+        }
+        let mut next = index.starts.clone();
+        index.items = vec![0; cells.len()];
+        for (item, &c) in cells.iter().enumerate() {
+            index.items[next[c]] = item;
+            next[c] += 1;
+        }
+        index
+    }
+
+    fn coord_of(&self, pos: Vec2) -> [usize; 2] {
+        [0, 1].map(|i| ((pos[i] / self.cell).max(0.0) as usize).min(self.dims[i] - 1))
+    }
+
+    fn cell_of(&self, pos: Vec2) -> usize {
+        let [x, y] = self.coord_of(pos);
+        y * self.dims[0] + x
+    }
+
+    /// Items in the cells overlapping the square of half-side `radius` around `pos`; callers
+    /// check exact distances.
+    pub fn near(&self, pos: Vec2, radius: f32) -> impl Iterator<Item = usize> + '_ {
+        let lo = self.coord_of(pos - Vec2::new(radius, radius));
+        let hi = self.coord_of(pos + Vec2::new(radius, radius));
+        (lo[1]..=hi[1])
+            .flat_map(move |y| (lo[0]..=hi[0]).map(move |x| y * self.dims[0] + x))
+            .flat_map(|c| &self.items[self.starts[c]..self.starts[c + 1]])
+            .copied()
+    }
+}
@@ -0,0 +1,57 @@
+use serde::Deserialize;
+
+use super::{
+    obstacle::{self, Shape},
+    render::Renderable,
+};
+use crate::math::Vec2;
+
+/// A region that scales the speed of agents inside it, like mud or a road.
+#[derive(Deserialize)]
+pub struct Terrain {
+    pub shape: Shape,
+    /// speed multiplier
+    pub speed: f32,
+}
+
+/// How agents measure the trips they make and the estimates they pass on.
+#[derive(Clone, Copy, Default, Deserialize)]
+pub enum Odometry {
+    /// distance travelled
+    #[default]
+    Length,
+    /// distance weighted by the inverse terrain speed, i.e. travel time at unit speed
+    Time,
+}
+
+impl Odometry {
+    /// Cost of a unit of distance in terrain of the given speed multiplier.
+    pub fn cost_scale(self, speed: f32) -> f32 {
+        match self {
+            Odometry::Length => 1.0,
+            Odometry::Time => 1.0 / speed,
+        }
+    }
+}
+
+/// Speed multiplier at a point; where regions overlap the last one listed applies.
+pub fn speed_at(terrain: &[Terrain], pos: Vec2) -> f32 {
+    terrain
+        .iter()
+        .rev()
+        .find(|region| obstacle::blocked(std::iter::once(region.shape.0.as_ref()), pos))
+        .map(|region| region.speed)
+        .unwrap_or(1.0)
+}
+
+impl Renderable for Terrain {
+    fn render(&self, args: super::render::Args) {
+        // Slow ground is drawn muddy, fast ground paved
+        let color = if self.speed < 1.0 {
+            [0x3a, 0x2f, 0x24]
+        } else {
+            [0x3a, 0x3d, 0x4a]
+        };
+        obstacle::fill(args, self.shape.0.as_ref(), color);
+    }
+}
@@ -0,0 +1,154 @@
+use serde::Deserialize;
+
+use crate::agent::State;
+
+/// Largest number of site kinds `held_karp` is run on; its cost grows as `2^n * n^2`.
+pub const MAX_HELD_KARP: usize = 16;
+
+/// Which sites an agent has to visit, and in what order, to complete a tour.
+#[derive(Clone, Default, Deserialize)]
+pub enum Tour {
+    /// after visiting every kind, target every kind but the current one again; no tours are
+    /// recorded
+    #[default]
+    Open,
+    /// visit every kind in any order, then return to the first one
+    Any,
+    /// visit these kinds in this order, cyclically
+    Ordered(Vec<usize>),
+}
+
+impl Tour {
+    /// Checks that an order only names kinds there are sites of.
+    pub fn check(&self, n_kinds: usize) -> Result<(), String> {
+        match self {
+            Tour::Ordered(order) if order.is_empty() => Err("the tour order is empty".into()),
+            Tour::Ordered(order) => match order.iter().find(|&&kind| kind >= n_kinds) {
+                Some(kind) => Err(format!("the tour visits kind {kind}, which has no sites")),
+                None => Ok(()),
+            },
+            _ => Ok(()),
+        }
+    }
+
+    /// Targeting flags for an agent that has not reached any site yet.
+    pub fn initial_targets(&self, n_kinds: usize) -> Vec<bool> {
+        match self {
+            Tour::Open | Tour::Any => vec![true; n_kinds],
+            Tour::Ordered(order) => (0..n_kinds).map(|kind| order.contains(&kind)).collect(),
+        }
+    }
+
+    /// Updates targeting after an agent reaches a targeted site of `kind`. Returns whether that
+    /// completed a tour.
+    pub fn arrive(&self, kind: usize, state: &mut State) -> bool {
+        let sites = &mut state.sites;
+        match self {
+            Tour::Open => {
+                if sites.iter().all(|site| !site.targeting) {
+                    sites
+                        .iter_mut()
+                        .enumerate()
+                        .for_each(|(i, site)| site.targeting = i != kind);
+                }
+                false
+            }
+            Tour::Any => {
+                // The first kind is only targeted again once every other kind has been visited
+                let completed = state.tour_start == Some(kind);
+                if state.tour_start.is_none() || completed {
+                    state.tour_start = Some(kind);
+                    sites
+                        .iter_mut()
+                        .enumerate()
+                        .for_each(|(i, site)| site.targeting = i != kind);
+                } else if sites.iter().all(|site| !site.targeting) {
+                    if let Some(start) = state.tour_start.and_then(|start| sites.get_mut(start)) {
+                        start.targeting = true;
+                    }
+                }
+                completed
+            }
+            Tour::Ordered(order) => {
+                let first = |kind| order.iter().position(|&k| k == kind);
+                // Carry on from the last step, as a kind alone doesn't say where in the order
+                // an agent is when it repeats
+                let pos = match state.tour_step.map(|step| (step + 1) % order.len()) {
+                    Some(pos) if order[pos] == kind => pos,
+                    _ => {
+                        let Some(pos) = first(kind) else {
+                            return false;
+                        };
+                        pos
+                    }
+                };
+                let start = *state.tour_start.get_or_insert(kind);
+                let completed = state.tour_step.is_some() && first(start) == Some(pos);
+                state.tour_step = Some(pos);
+                let next = order[(pos + 1) % order.len()];
+                sites
+                    .iter_mut()
+                    .enumerate()
+                    .for_each(|(i, site)| site.targeting = i == next);
+                completed
+            }
+        }
+    }
+}
+
+/// Exact shortest closed tour through `kinds`, given distances between every pair of kinds.
+/// -> (length, kinds in visiting order)
+pub fn held_karp(dist: &[Vec<f32>], kinds: &[usize]) -> Option<(f32, Vec<usize>)> {
+    let n = kinds.len();
+    if n == 0 || n > MAX_HELD_KARP {
+        return None;
+    }
+    let d = |a: usize, b: usize| dist[kinds[a]][kinds[b]];
+
+    // best[set][last]: shortest path from kinds[0] through `set` ending at `last`, where `set`
+    // is a bitmask over kinds[1..] and `last` is in it
+    let n_sets = 1 << (n - 1);
+    let mut best = vec![vec![f32::INFINITY; n]; n_sets];
+    let mut prev = vec![vec![usize::MAX; n]; n_sets];
+    for last in 1..n {
+        best[1 << (last - 1)][last] = d(0, last);
+    }
+    for set in 1..n_sets {
+        for last in (1..n).filter(|last| set & (1 << (last - 1)) != 0) {
+            let rest = set & !(1 << (last - 1));
+            if rest == 0 {
+                continue;
+            }
+            for mid in (1..n).filter(|mid| rest & (1 << (mid - 1)) != 0) {
+                let len = best[rest][mid] + d(mid, last);
+                if len < best[set][last] {
+                    best[set][last] = len;
+                    prev[set][last] = mid;
+                }
+            }
+        }
+    }
+
+    if n == 1 {
+        return Some((0.0, kinds.to_vec()));
+    }
+    let full = n_sets - 1;
+    let (len, mut last) = (1..n)
+        .map(|last| (best[full][last] + d(last, 0), last))
+        .min_by(|a, b| a.0.total_cmp(&b.0))?;
+    if !len.is_finite() {
+        return None;
+    }
+
+    let mut order = Vec::with_capacity(n);
+    let mut set = full;
+    while last != usize::MAX && last != 0 {
+        order.push(kinds[last]);
+        let mid = prev[set][last];
+        set &= !(1 << (last - 1));
+        last = if set == 0 { 0 } else { mid };
+    }
+    order.push(kinds[0]);
+    order.reverse();
+    Some((len, order))
+}
@@ -0,0 +1,192 @@
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::sim::World;
+
+/// Bootstrap resamples behind each confidence interval.
+const RESAMPLES: usize = 10_000;
+/// Coverage of the confidence intervals.
+const CONFIDENCE: f32 = 0.95;
+
+/// How one seeded run went.
+pub struct Trial {
+    pub seed: u64,
+    /// simulated seconds until the best trip came within the tolerance of the optimum; NaN if it
+    /// never did
+    pub time_within: f32,
+    /// best trip at the end of the run over the shortest possible; NaN if no agent made a trip
+    pub ratio: f32,
+}
+
+impl Trial {
+    /// Runs `world` for `duration` simulated seconds, watching the best trip any agent makes
+    /// between two kinds against `optimal`. `within` is the tolerance as a fraction.
+    pub fn run(
+        mut world: World,
+        seed: u64,
+        duration: f32,
+        tick: f32,
+        optimal: f32,
+        within: f32,
+    ) -> Self {
+        let best = |world: &World| {
+            let dist = world.shortest_dist();
+            if dist < f32::MAX {
+                dist
+            } else {
+                f32::NAN
+            }
+        };
+        let mut time_within = f32::NAN;
+        while world.time < duration {
+            world.update(tick);
+            if time_within.is_nan() && best(&world) <= optimal * (1.0 + within) {
+                time_within = world.time;
+            }
+        }
+        Self {
+            seed,
+            time_within,
+            ratio: best(&world) / optimal,
+        }
+    }
+}
+
+/// Shortest distance between sites of two different kinds, which the best trip approaches.
+pub fn optimal(world: &World) -> Option<f32> {
+    let dist = world.kind_distances();
+    dist.iter()
+        .enumerate()
+        .flat_map(|(from, row)| row.iter().enumerate().filter(move |&(to, _)| to != from))
+        .map(|(_, &d)| d)
+        .filter(|d| d.is_finite())
+        .min_by(f32::total_cmp)
+}
+
+/// Summary statistics of one measure over the runs that produced a value.
+pub struct Summary {
+    /// runs with a value
+    pub n: usize,
+    pub mean: f32,
+    /// sample standard deviation
+    pub sd: f32,
+    pub min: f32,
+    pub q1: f32,
+    pub median: f32,
+    pub q3: f32,
+    pub max: f32,
+    /// percentile bootstrap interval for the mean
+    pub ci: (f32, f32),
+}
+
+impl Summary {
+    /// Summarises the finite `values`. The bootstrap is seeded so the same runs always give the
+    /// same interval.
+    pub fn of(values: impl Iterator<Item = f32>) -> Self {
+        let mut values: Vec<f32> = values.filter(|v| v.is_finite()).collect();
+        values.sort_by(f32::total_cmp);
+        let n = values.len();
+        let mean = values.iter().sum::<f32>() / n as f32;
+        let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f32>() / (n as f32 - 1.0);
+
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let mut means: Vec<f32> = (0..if n > 0 { RESAMPLES } else { 0 })
+            .map(|_| (0..n).map(|_| values[rng.gen_range(0..n)]).sum::<f32>() / n as f32)
+            .collect();
+        means.sort_by(f32::total_cmp);
+        let tail = (1.0 - CONFIDENCE) / 2.0;
+
+        Self {
+            n,
+            mean,
+            sd: if n > 1 { variance.sqrt() } else { f32::NAN },
+            min: quantile(&values, 0.0),
+            q1: quantile(&values, 0.25),
+            median: quantile(&values, 0.5),
+            q3: quantile(&values, 0.75),
+            max: quantile(&values, 1.0),
+            ci: (quantile(&means, tail), quantile(&means, 1.0 - tail)),
+        }
+    }
+
+    fn fields(&self) -> [f32; 9] {
+        [
+            self.mean,
+            self.sd,
+            self.min,
+            self.q1,
+            self.median,
+            self.q3,
+            self.max,
+            self.ci.0,
+            self.ci.1,
+        ]
+    }
+}
+
+/// Linearly interpolated quantile of sorted `values`; NaN if there are none.
+fn quantile(values: &[f32], q: f32) -> f32 {
+    if values.is_empty() {
+        return f32::NAN;
+    }
+    let pos = q * (values.len() - 1) as f32;
+    let (lo, hi) = (pos.floor() as usize, pos.ceil() as usize);
+    values[lo] + (values[hi] - values[lo]) * (pos - lo as f32)
+}
+
+/// Prints every run and the summaries as tab separated lines on stdout, and a table of the
+/// summaries on stderr. Run lines hold the seed, time to within tolerance and final ratio;
+/// summary lines hold the measure, the number of runs it covers, then the mean, sd, min, q1,
+/// median, q3, max and confidence interval.
+pub fn report(trials: &[Trial], within: f32) {
+    for trial in trials {
+        println!(
+            "run\t{}\t{}\t{}",
+            trial.seed, trial.time_within, trial.ratio
+        );
+    }
+    let measures = [
+        (
+            format!("time to within {}%", within * 100.0),
+            "time_within",
+            Summary::of(trials.iter().map(|t| t.time_within)),
+        ),
+        (
+            "final path ratio".to_string(),
+            "ratio",
+            Summary::of(trials.iter().map(|t| t.ratio)),
+        ),
+    ];
+    for (_, key, summary) in &measures {
+        let fields: Vec<String> = summary.fields().iter().map(f32::to_string).collect();
+        println!("summary\t{key}\t{}\t{}", summary.n, fields.join("\t"));
+    }
+
+    let width = measures
+        .iter()
+        .map(|(name, ..)| name.len())
+        .max()
+        .unwrap_or(0);
+    eprintln!(
+        "{:width$}  {:>5}  {:>8}  {:>8}  {:>8}  {:>8}  {:>8}  {:>8}  {:>8}  {:>19}",
+        "",
+        "runs",
+        "mean",
+        "sd",
+        "min",
+        "q1",
+        "median",
+        "q3",
+        "max",
+        format!("{}% CI of mean", CONFIDENCE * 100.0),
+    );
+    for (name, _, summary) in &measures {
+        let [mean, sd, min, q1, median, q3, max, lo, hi] = summary.fields();
+        eprintln!(
+            "{name:width$}  {:>5}  {mean:>8.3}  {sd:>8.3}  {min:>8.3}  {q1:>8.3}  {median:>8.3}  \
+             {q3:>8.3}  {max:>8.3}  {:>19}",
+            format!("{}/{}", summary.n, trials.len()),
+            format!("{lo:.3} to {hi:.3}"),
+        );
+    }
+}
@@ -0,0 +1,56 @@
+//! Agents go no further than their energy takes them, and recharge somewhere that exists.
+
+use sales_ants::{load_scene, start};
+
+/// A scene with an open field and `energy` as its energy settings.
+fn scene(energy: &str, nest: bool) -> String {
+    let nest = if nest {
+        "nest: Some(Nest(pos: (15.5, 0.5), size: 0.5)),"
+    } else {
+        ""
+    };
+    format!(
+        "World(
+            world_size: (16, 10),
+            {nest}
+            energy: Some(({energy})),
+            sites: [
+                Site(pos: (4, 2), kind: 0, size: 0.2),
+                Site(pos: (12, 8), kind: 1, size: 0.2),
+            ],
+            site_kinds: [(0xff, 0x00, 0x00), (0x00, 0xff, 0x00)],
+            obstacles: [],
+        )"
+    )
+}
+
+#[test]
+fn steps_stop_when_energy_runs_out() {
+    let capacity = 0.05;
+    let mut world = start(
+        &scene(&format!("capacity: {capacity}, move_cost: 1.0"), false),
+        Some(1),
+    );
+    let before: Vec<_> = world.agents.iter().map(|a| a.pos).collect();
+    // Long enough for every agent to go well past what it can afford
+    world.update(1.0);
+    for (agent, start) in world.agents.iter().zip(before) {
+        let moved = (agent.pos - start).mag();
+        assert!(
+            moved <= capacity + 1e-4,
+            "moved {moved} on {capacity} energy"
+        );
+        assert!(agent.energy >= 0.0);
+    }
+}
+
+#[test]
+#[should_panic(expected = "recharge at the nest")]
+fn recharging_at_a_missing_nest_is_rejected() {
+    load_scene(&scene("recharge_at: Nest", false));
+}
+
+#[test]
+fn recharging_at_a_nest_is_fine_when_there_is_one() {
+    load_scene(&scene("recharge_at: Both", true));
+}
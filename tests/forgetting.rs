@@ -0,0 +1,62 @@
+//! Agents forget estimates at rates that make sense, and only estimates they have.
+
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use sales_ants::{
+    agent::{Agent, Forgetting},
+    load_scene,
+    math::Vec2,
+};
+
+/// A scene with two sites and `forgetting` as its forgetting.
+fn scene(forgetting: &str) -> String {
+    format!(
+        "World(
+            world_size: (16, 10),
+            forgetting: {forgetting},
+            sites: [
+                Site(pos: (4, 2), kind: 0, size: 0.2),
+                Site(pos: (12, 8), kind: 1, size: 0.2),
+            ],
+            site_kinds: [(0xff, 0x00, 0x00), (0x00, 0xff, 0x00)],
+            obstacles: [],
+        )"
+    )
+}
+
+/// An agent heading for kind 0, which it has an estimate of `sq_dist` for from time zero, as
+/// `target_dist`.
+fn agent(sq_dist: f32, target_dist: f32) -> Agent {
+    let world = load_scene(&scene("Never"));
+    let mut agent = Agent::random(Vec2::new(8.0, 5.0), &mut ChaCha8Rng::seed_from_u64(0));
+    world.setup_agent(&mut agent);
+    agent.state.sites[0].sq_dist = sq_dist;
+    agent.state.sites[0].time = 0.0;
+    agent.state.target = Some((0, target_dist));
+    agent
+}
+
+#[test]
+fn rates_must_not_be_negative() {
+    for forgetting in ["Decay(-0.1)", "Decay(NaN)", "Reset(-1.0)", "Reset(NaN)"] {
+        let result = std::panic::catch_unwind(|| load_scene(&scene(forgetting)));
+        assert!(result.is_err(), "{forgetting} was accepted");
+    }
+    load_scene(&scene("Decay(0.1)"));
+    load_scene(&scene("Reset(5.0)"));
+}
+
+#[test]
+fn forgotten_targets_are_dropped() {
+    let mut agent = agent(4.0, 4.0);
+    agent.forget(Forgetting::Reset(1.0), 2.0, 0.1);
+    assert!(agent.state.target.is_none());
+}
+
+#[test]
+fn targets_without_an_estimate_are_kept() {
+    // As ACO agents' are
+    let mut agent = agent(4.0, f32::INFINITY);
+    agent.forget(Forgetting::Decay(0.1), 2.0, 0.1);
+    assert_eq!(agent.state.target, Some((0, f32::INFINITY)));
+}
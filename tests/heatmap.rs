@@ -0,0 +1,57 @@
+//! The heatmap keeps each trip's traffic to the pair of kinds it's between.
+
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use sales_ants::{agent::Agent, load_scene, math::Vec2, sim::heatmap};
+
+const SCENE: &str = "World(
+    world_size: (16, 10),
+    sites: [
+        Site(pos: (2, 5), kind: 0, size: 0.2),
+        Site(pos: (8, 5), kind: 1, size: 0.2),
+        Site(pos: (14, 5), kind: 2, size: 0.2),
+    ],
+    site_kinds: [(0xff, 0x00, 0x00), (0x00, 0xff, 0x00), (0x00, 0x00, 0xff)],
+    obstacles: [],
+)";
+
+/// A worker at `pos` that last visited `from` and is heading for `to`.
+fn worker(rng: &mut ChaCha8Rng, pos: Vec2, from: usize, to: usize) -> Agent {
+    let mut agent = Agent::random(pos, rng);
+    agent.is_scout = false;
+    agent.state.last_site = Some(from);
+    agent.state.target = Some((to, 1.0));
+    agent
+}
+
+#[test]
+fn traffic_is_kept_per_pair_of_kinds() {
+    let mut world = load_scene(SCENE);
+    let mut rng = ChaCha8Rng::seed_from_u64(0);
+    let (near, far) = (Vec2::new(5.0, 5.0), Vec2::new(11.0, 5.0));
+    world.agents = vec![worker(&mut rng, near, 0, 1), worker(&mut rng, far, 2, 1)];
+
+    let mut map = heatmap::new(&world);
+    heatmap::accumulate(&mut map, &world, 1.0);
+
+    let at = |a, b, pos| map.layers[heatmap::layer(3, a, b)][map.idx_of(pos).unwrap()];
+    assert_eq!(at(0, 1, near), 1.0);
+    assert_eq!(at(1, 0, near), 1.0);
+    assert_eq!(
+        at(0, 1, far),
+        0.0,
+        "a trip from 2 to 1 counted towards 0 and 1"
+    );
+    assert_eq!(at(1, 2, far), 1.0);
+    assert_eq!(at(0, 2, near) + at(0, 2, far), 0.0);
+}
+
+#[test]
+fn layers_cover_every_pair_once() {
+    let n = 5;
+    let mut layers: Vec<usize> = (0..n)
+        .flat_map(|a| (a + 1..n).map(move |b| heatmap::layer(n, a, b)))
+        .collect();
+    layers.sort();
+    assert_eq!(layers, (0..n * (n - 1) / 2).collect::<Vec<_>>());
+}
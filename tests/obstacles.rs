@@ -0,0 +1,299 @@
+//! Randomised checks, over many shapes of every obstacle type, that `bounding_box`, `inside`,
+//! `intersects`, `occludes` and `process_collision` agree with each other, and that scenes can
+//! only hold shapes they work for.
+
+use std::{f32::consts::TAU, ops::Range};
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use sales_ants::{
+    math::Vec2,
+    sim::obstacle::{self, Circle, InvRect, Obstacle, Polygon, Rect, Triangle},
+};
+
+const CASES: usize = 20_000;
+/// Distance from a boundary at which a point is clearly on one side of it.
+const EPSILON: f32 = 1e-3;
+
+/// A random obstacle of every type in turn, with the area around it that points are drawn from.
+fn shape(rng: &mut ChaCha8Rng, case: usize) -> (Box<dyn Obstacle>, [Range<f32>; 2]) {
+    let center = Vec2::new(rng.gen_range(2.0..14.0), rng.gen_range(2.0..8.0));
+    let radius = rng.gen_range(0.2..2.0);
+    let obstacle: Box<dyn Obstacle> = match case % 5 {
+        0 => Box::new(Circle { center, radius }),
+        1 => Box::new(Rect {
+            ranges: [0, 1].map(|i| center[i] - radius..center[i] + rng.gen_range(0.2..2.0)),
+        }),
+        2 => Box::new(Triangle {
+            verts: convex(rng, center, radius, 3).try_into().unwrap(),
+        }),
+        3 => {
+            let n = rng.gen_range(3..9);
+            Box::new(Polygon {
+                verts: convex(rng, center, radius, n),
+            })
+        }
+        _ => {
+            let size = Vec2::new(rng.gen_range(4.0..16.0), rng.gen_range(4.0..10.0));
+            let walls = InvRect(Rect {
+                ranges: [0.0..size.x, 0.0..size.y],
+            });
+            return (Box::new(walls), [-1.0..size.x + 1.0, -1.0..size.y + 1.0]);
+        }
+    };
+    let area = obstacle.bounding_box().map(|r| r.start - 1.0..r.end + 1.0);
+    (obstacle, area)
+}
+
+/// Counterclockwise corners of a convex polygon around `center`.
+fn convex(rng: &mut ChaCha8Rng, center: Vec2, radius: f32, n: usize) -> Vec<Vec2> {
+    let start = rng.gen_range(0.0..TAU);
+    // Evenly spread corners, each nudged by less than half the gap to its neighbours
+    (0..n)
+        .map(|i| start + (i as f32 + rng.gen_range(-0.4..0.4)) * TAU / n as f32)
+        .map(|angle| center + radius * Vec2::new(angle.cos(), angle.sin()))
+        .collect()
+}
+
+fn point(rng: &mut ChaCha8Rng, area: &[Range<f32>; 2]) -> Vec2 {
+    Vec2::new(
+        rng.gen_range(area[0].clone()),
+        rng.gen_range(area[1].clone()),
+    )
+}
+
+/// A random motion from a random point, sometimes along an axis and sometimes not at all.
+fn motion(rng: &mut ChaCha8Rng, area: &[Range<f32>; 2]) -> (Vec2, Vec2) {
+    let origin = point(rng, area);
+    let mut delta = point(rng, area) - origin;
+    match rng.gen_range(0..10) {
+        0 => delta.x = 0.0,
+        1 => delta.y = 0.0,
+        2 if rng.gen_bool(0.1) => delta = Vec2::new(0.0, 0.0),
+        _ => {}
+    }
+    (origin, delta)
+}
+
+fn blocked(obstacle: &dyn Obstacle, pos: Vec2) -> bool {
+    obstacle::blocked(std::iter::once(obstacle), pos)
+}
+
+fn finite(v: Vec2) -> bool {
+    v.x.is_finite() && v.y.is_finite()
+}
+
+#[test]
+fn inside_lies_within_bounding_box() {
+    let mut rng = ChaCha8Rng::seed_from_u64(0);
+    for case in 0..CASES {
+        let (obstacle, area) = shape(&mut rng, case);
+        let bbox = obstacle.bounding_box();
+        let pos = point(&mut rng, &area);
+        let within = [0, 1].map(|i| bbox[i].start <= pos[i] && pos[i] <= bbox[i].end);
+        assert!(
+            !obstacle.inside(pos) || within == [true; 2],
+            "case {case}: {pos:?} is inside but outside the bounding box {bbox:?}",
+        );
+    }
+}
+
+#[test]
+fn hits_are_finite_and_on_the_boundary() {
+    let mut rng = ChaCha8Rng::seed_from_u64(1);
+    for case in 0..CASES {
+        let (obstacle, area) = shape(&mut rng, case);
+        let (origin, ray) = motion(&mut rng, &area);
+        let hits = obstacle.intersects(origin, ray);
+        let step = EPSILON / ray.mag();
+        for (t, norm) in hits.iter().map(|hit| (hit.t(), hit.normal())) {
+            assert!(
+                t.is_finite() && finite(norm),
+                "case {case}: hit ({t}, {norm:?}) from {origin:?} along {ray:?}",
+            );
+            assert!(
+                (norm.mag() - 1.0).abs() < EPSILON,
+                "case {case}: normal {norm:?} isn't a unit vector",
+            );
+            let hit = origin + t * ray;
+            assert!(
+                !blocked(obstacle.as_ref(), hit + EPSILON * norm),
+                "case {case}: normal {norm:?} at {hit:?} doesn't point out",
+            );
+
+            // Just past an entry, or just short of an exit, is inside; a ray that only grazes
+            // the shape leaves no room to check
+            if hits
+                .iter()
+                .any(|other| other.t() != t && (other.t() - t).abs() < 2.0 * step)
+            {
+                continue;
+            }
+            let within = if ray.dot(norm) < 0.0 {
+                t + step
+            } else {
+                t - step
+            };
+            assert!(
+                blocked(obstacle.as_ref(), origin + within * ray),
+                "case {case}: hit {hit:?} from {origin:?} along {ray:?} isn't on the boundary",
+            );
+        }
+    }
+}
+
+#[test]
+fn crossings_are_hit() {
+    let mut rng = ChaCha8Rng::seed_from_u64(2);
+    for case in 0..CASES {
+        let (obstacle, area) = shape(&mut rng, case);
+        let (origin, delta) = motion(&mut rng, &area);
+        if blocked(obstacle.as_ref(), origin) == blocked(obstacle.as_ref(), origin + delta) {
+            continue;
+        }
+        let hits = obstacle.intersects(origin, delta);
+        assert!(
+            hits.iter().any(|hit| (0.0..=1.0).contains(&hit.t())),
+            "case {case}: {origin:?} to {:?} crosses the boundary without a hit in {hits:?}",
+            origin + delta,
+        );
+    }
+}
+
+#[test]
+fn occlusion_matches_the_segment() {
+    let mut rng = ChaCha8Rng::seed_from_u64(5);
+    for case in 0..CASES {
+        let (obstacle, area) = shape(&mut rng, case);
+        let (from, delta) = motion(&mut rng, &area);
+        let to = from + delta;
+        let occluded = obstacle.occludes(from, to);
+
+        // A segment clear of the bounding box can't be occluded
+        let bbox = obstacle.bounding_box();
+        let clear = (0..2).any(|i| {
+            from[i].max(to[i]) < bbox[i].start - EPSILON
+                || from[i].min(to[i]) > bbox[i].end + EPSILON
+        });
+        assert!(
+            !(clear && occluded),
+            "case {case}: {from:?} to {to:?} is occluded from outside {bbox:?}",
+        );
+
+        // One running through the shape is, unless it only starts on or ends in it
+        let through = (1..100)
+            .map(|i| from + (i as f32 / 100.0) * delta)
+            .any(|pos| blocked(obstacle.as_ref(), pos));
+        if through && !blocked(obstacle.as_ref(), from) && !blocked(obstacle.as_ref(), to) {
+            assert!(
+                occluded,
+                "case {case}: {from:?} to {to:?} passes through unoccluded"
+            );
+        }
+    }
+}
+
+#[test]
+fn collisions_need_an_end_inside() {
+    let mut rng = ChaCha8Rng::seed_from_u64(3);
+    for case in 0..CASES {
+        let (obstacle, area) = shape(&mut rng, case);
+        let (origin, delta) = motion(&mut rng, &area);
+        if blocked(obstacle.as_ref(), origin) {
+            continue;
+        }
+        let collision = obstacle.process_collision(origin, delta);
+        assert_eq!(
+            collision.is_some(),
+            blocked(obstacle.as_ref(), origin + delta),
+            "case {case}: {origin:?} to {:?} gave {collision:?}",
+            origin + delta,
+        );
+    }
+}
+
+#[test]
+fn bounces_never_end_inside() {
+    let mut rng = ChaCha8Rng::seed_from_u64(4);
+    for case in 0..CASES {
+        let (obstacle, area) = shape(&mut rng, case);
+        let (mut origin, mut delta) = motion(&mut rng, &area);
+        if blocked(obstacle.as_ref(), origin) {
+            continue;
+        }
+        let start = (origin, delta);
+        // Two bounces get out of any corner of an `InvRect`
+        for _ in 0..2 {
+            let Some((hit, rest)) = obstacle.process_collision(origin, delta) else {
+                break;
+            };
+            assert!(
+                finite(hit) && finite(rest) && !blocked(obstacle.as_ref(), hit),
+                "case {case}: bouncing {start:?} hit at {hit:?} and went on by {rest:?}",
+            );
+            assert!(
+                (rest.mag() - (origin + delta - hit).mag()).abs() < EPSILON,
+                "case {case}: bouncing {start:?} changed the remaining distance",
+            );
+            (origin, delta) = (hit, rest);
+        }
+        assert!(
+            !blocked(obstacle.as_ref(), origin + delta),
+            "case {case}: bouncing {start:?} ended inside at {:?}",
+            origin + delta,
+        );
+    }
+}
+
+#[test]
+fn generated_shapes_pass_the_scene_checks() {
+    let mut rng = ChaCha8Rng::seed_from_u64(6);
+    for case in 0..CASES / 10 {
+        let n = rng.gen_range(3..9);
+        let verts = convex(&mut rng, Vec2::new(8.0, 5.0), 1.0, n);
+        assert!(
+            Polygon::try_from(verts.clone()).is_ok(),
+            "case {case}: {verts:?} was rejected",
+        );
+    }
+}
+
+#[test]
+fn shapes_that_arent_convex_and_counterclockwise_are_rejected() {
+    for verts in [
+        // Clockwise
+        "[(0, 0), (0, 1), (1, 1), (1, 0)]",
+        // Dented
+        "[(0, 0), (2, 0), (1, 0.5), (2, 2), (0, 2)]",
+        // Winds twice
+        "[(1, 0), (-0.81, 0.59), (0.31, -0.95), (0.31, 0.95), (-0.81, -0.59)]",
+        // Too few corners
+        "[(0, 0), (1, 0)]",
+    ] {
+        assert!(
+            ron::from_str::<Polygon>(verts).is_err(),
+            "{verts} was accepted"
+        );
+    }
+    assert!(ron::from_str::<Triangle>("((0, 0), (0, 1), (1, 0))").is_err());
+    assert!(ron::from_str::<Triangle>("((0, 0), (1, 0), (0, 1))").is_ok());
+}
+
+#[test]
+fn hits_hold_a_line_through_opposite_corners() {
+    // Each corner the line passes through is on two edges
+    let square: Polygon = ron::from_str("[(0, 0), (1, 0), (1, 1), (0, 1)]").unwrap();
+    let hits = square.intersects(Vec2::new(-1.0, -1.0), Vec2::new(1.0, 1.0));
+    assert_eq!(hits.len(), 4, "{hits:?}");
+    assert!(hits.iter().all(|hit| hit.t() == 1.0 || hit.t() == 2.0));
+}
+
+#[test]
+#[should_panic(expected = "more crossings than a convex shape has")]
+fn hits_beyond_capacity_are_caught() {
+    let hit = obstacle::Hit::new(0.0, Vec2::new(1.0, 0.0)).unwrap();
+    let mut hits = obstacle::Hits::default();
+    for _ in 0..5 {
+        hits.push(hit);
+    }
+}
@@ -0,0 +1,81 @@
+//! Births and failures keep the swarm to the size the scene asks for and count what was lost.
+
+use sales_ants::{agent::Failure, start};
+
+const TICK: f32 = 0.1;
+
+/// A failure-prone swarm with a nest to replace it, with `events` as its mass failures.
+fn scene(events: &str) -> String {
+    format!(
+        "World(
+            world_size: (16, 10),
+            nest: Some(Nest(pos: (15.5, 0.5), size: 0.5)),
+            population: (
+                spawn_rate: 200.0,
+                max_agents: Some(2000),
+                failure_rate: 0.2,
+                mass_failures: [{events}],
+            ),
+            sites: [
+                Site(pos: (4, 2), kind: 0, size: 0.2),
+                Site(pos: (12, 2), kind: 1, size: 0.2),
+            ],
+            site_kinds: [(0xff, 0x00, 0x00), (0x00, 0xff, 0x00)],
+            obstacles: [],
+        )"
+    )
+}
+
+#[test]
+fn new_agents_replace_wrecks() {
+    let mut world = start(&scene(""), Some(1));
+    while world.time < 10.0 {
+        world.update(TICK);
+    }
+    let census = world.census();
+    assert!(census.crashed > 1000, "only {} crashed", census.crashed);
+    assert!(
+        world.agents.len() < 2000 + census.spawned / 2,
+        "{} agents after {} spawned",
+        world.agents.len(),
+        census.spawned,
+    );
+}
+
+#[test]
+fn mass_failures_at_the_start_happen() {
+    // The swarm starts in the top right corner
+    let event = "(time: 0.0, center: (15.5, 0.5), radius: 2.0, fraction: 1.0, failure: Crash)";
+    let mut world = start(&scene(event), Some(1));
+    world.update(TICK);
+    let wrecks = world
+        .agents
+        .iter()
+        .filter(|agent| agent.failure == Some(Failure::Crash))
+        .count();
+    assert!(wrecks > 1000, "only {wrecks} crashed");
+}
+
+#[test]
+fn what_wrecks_made_outlasts_them() {
+    let mut world = start(&scene(""), Some(1));
+    for agent in &mut world.agents {
+        agent.failure = Some(Failure::Crash);
+    }
+    let wreck = &mut world.agents[0];
+    wreck.shortest_dist = 1.5;
+    wreck.routes.record(0, 1, 1.5);
+    wreck.energy_spent = 7.0;
+    let trips = world.routes().trips[1];
+
+    world.update(TICK);
+    assert_eq!(
+        world.agents[0].shortest_dist,
+        f32::MAX,
+        "the wreck's slot wasn't reused"
+    );
+    assert_eq!(world.shortest_dist(), 1.5);
+    assert_eq!(world.routes().get(0, 1).0, 1.5);
+    assert!(world.routes().trips[1] >= trips);
+    assert!(world.energy_spent() >= 7.0);
+}
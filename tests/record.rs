@@ -0,0 +1,84 @@
+//! Recordings play back the targets agents had, up to as many site kinds as they can hold.
+
+use sales_ants::{
+    load_scene,
+    record::{Recorder, Recording, MAX_KINDS},
+    start,
+};
+
+/// A scene with one site of each of `n_kinds` kinds.
+fn scene(n_kinds: usize) -> String {
+    let sites: String = (0..n_kinds)
+        .map(|kind| format!("Site(pos: (8, 5), kind: {kind}, size: 0.2),"))
+        .collect();
+    let colors = "(0xff, 0xff, 0xff),".repeat(n_kinds);
+    format!(
+        "World(
+            world_size: (16, 10),
+            sites: [{sites}],
+            site_kinds: [{colors}],
+            obstacles: [],
+        )"
+    )
+}
+
+fn path(name: &str) -> String {
+    let dir = std::env::temp_dir();
+    dir.join(format!("sales-ants-{name}-{}.rec", std::process::id()))
+        .to_str()
+        .unwrap()
+        .to_owned()
+}
+
+#[test]
+fn every_tick_is_recorded_unless_down_sampled() {
+    let scene = scene(2);
+    let frames = |name: &str, fps| {
+        let mut world = start(&scene, Some(1));
+        let path = path(name);
+        let mut recorder = Recorder::create(&path, &world, &scene, fps);
+        // Ticks that add up exactly, so frames fall due on them
+        for _ in 0..10 {
+            world.update(0.25);
+            recorder.record(&world);
+        }
+        let recording = Recording::open(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        recording.times
+    };
+    assert_eq!(frames("every-tick", None).len(), 10);
+    assert_eq!(
+        frames("down-sampled", Some(2.0)),
+        [0.25, 0.5, 1.0, 1.5, 2.0, 2.5]
+    );
+}
+
+#[test]
+fn the_last_kind_plays_back() {
+    let scene = scene(MAX_KINDS);
+    let mut world = load_scene(&scene);
+    world.agents = sales_ants::sim::World::random_agents(&mut world.rng);
+    world.agents[0].state.target = Some((MAX_KINDS - 1, 1.0));
+    world.agents[1].state.target = None;
+
+    let path = path("last-kind");
+    Recorder::create(&path, &world, &scene, None).record(&world);
+    let recording = Recording::open(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let mut shown = load_scene(&recording.scene);
+    recording.show(0, &mut shown);
+    assert_eq!(
+        shown.agents[0].state.target.map(|t| t.0),
+        Some(MAX_KINDS - 1)
+    );
+    assert!(shown.agents[1].state.target.is_none());
+}
+
+#[test]
+#[should_panic(expected = "recordings hold up to")]
+fn scenes_with_too_many_kinds_are_refused() {
+    let scene = scene(MAX_KINDS + 1);
+    let world = load_scene(&scene);
+    Recorder::create(&path("too-many"), &world, &scene, None);
+}
@@ -0,0 +1,84 @@
+//! Runs every scene in `scenes/` headless from a fixed seed, checking that the swarm keeps out of
+//! obstacles and inside the world, and that the best trip ends up where it did when the scene
+//! was last checked.
+
+use sales_ants::{sim::obstacle, start};
+
+const SEED: u64 = 1;
+const TICK: f32 = 0.05;
+/// Relative difference allowed between the best trip and the recorded one. Much shorter is as
+/// suspect as much longer: it usually means agents got through an obstacle.
+const TOLERANCE: f32 = 0.1;
+
+/// Simulates `scenes/{name}.ron` for `duration` seconds and compares the best trip with `best`.
+fn check(name: &str, duration: f32, best: f32) {
+    let path = format!("{}/scenes/{name}.ron", env!("CARGO_MANIFEST_DIR"));
+    let scene = std::fs::read_to_string(&path).unwrap();
+    let mut world = start(&scene, Some(SEED));
+
+    while world.time < duration {
+        world.update(TICK);
+        for (idx, agent) in world.agents.iter().enumerate() {
+            let pos = agent.pos;
+            assert!(
+                (0.0..=world.world_size.x).contains(&pos.x)
+                    && (0.0..=world.world_size.y).contains(&pos.y),
+                "{name}: agent {idx} left the world at {pos:?} after {} s",
+                world.time,
+            );
+            assert!(
+                !obstacle::blocked(world.obstacles.iter().map(Box::as_ref), pos),
+                "{name}: agent {idx} is inside an obstacle at {pos:?} after {} s",
+                world.time,
+            );
+        }
+    }
+
+    let found = world.shortest_dist();
+    assert!(
+        (found - best).abs() <= TOLERANCE * best,
+        "{name}: best trip after {duration} s is {found}, expected {best}",
+    );
+}
+
+macro_rules! scenes {
+    ($($test:ident: $name:literal for $duration:literal s => $best:literal,)*) => {
+        $(
+            #[test]
+            fn $test() {
+                check($name, $duration, $best);
+            }
+        )*
+
+        #[test]
+        fn every_scene_is_checked() {
+            let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/scenes");
+            let checked = [$($name),*];
+            for entry in std::fs::read_dir(dir).unwrap() {
+                let path = entry.unwrap().path();
+                let name = path.file_stem().unwrap().to_str().unwrap();
+                assert!(checked.contains(&name), "no test for {}", path.display());
+            }
+        }
+    };
+}
+
+scenes! {
+    aco: "aco" for 120.0 s => 11.86,
+    circles: "circles" for 30.0 s => 13.00,
+    column: "column" for 30.0 s => 20.24,
+    columns: "columns" for 60.0 s => 36.47,
+    columns2: "columns2" for 60.0 s => 26.19,
+    columns3: "columns3" for 30.0 s => 16.55,
+    crowding: "crowding" for 60.0 s => 16.48,
+    default: "default" for 30.0 s => 2.29,
+    depot: "depot" for 30.0 s => 9.93,
+    energy: "energy" for 30.0 s => 20.24,
+    failures: "failures" for 30.0 s => 20.02,
+    moving: "moving" for 30.0 s => 5.51,
+    pheromone: "pheromone" for 120.0 s => 9.90,
+    river: "river" for 30.0 s => 8.27,
+    small_gap: "small-gap" for 60.0 s => 16.23,
+    terrain: "terrain" for 30.0 s => 9.18,
+    tour: "tour" for 30.0 s => 2.36,
+}
@@ -0,0 +1,61 @@
+//! Site bookkeeping and motion that the scenes don't pin down.
+
+use sales_ants::sim::site::{Desk, Motion, Site};
+
+fn site(motion: &str) -> Site {
+    ron::from_str(&format!(
+        "(pos: (0, 0), kind: 0, size: 0.1, motion: {motion})"
+    ))
+    .unwrap()
+}
+
+#[test]
+fn mean_wait_is_zero_before_anyone_is_admitted() {
+    let desk = Desk::default();
+    assert_eq!(desk.mean_wait(), 0.0);
+}
+
+#[test]
+fn mean_wait_averages_over_admitted_agents() {
+    let desk = Desk {
+        admitted: 4,
+        waited: 10.0,
+        ..Default::default()
+    };
+    assert_eq!(desk.mean_wait(), 2.5);
+}
+
+#[test]
+fn paths_through_repeated_points_stay_finite() {
+    for closed in [false, true] {
+        let mut site = site(&format!(
+            "Path((points: [(1, 1), (1, 1), (3, 1), (3, 1)], speed: 0.5, closed: {closed}))"
+        ));
+        for step in 0..200 {
+            site.update(step as f32 * 0.1);
+            let pos = site.pos;
+            assert!(
+                pos.x.is_finite() && pos.y.is_finite() && (1.0..=3.0).contains(&pos.x),
+                "closed: {closed}, {pos:?} at step {step}",
+            );
+        }
+    }
+}
+
+#[test]
+fn paths_that_go_nowhere_stay_at_their_point() {
+    let mut site = site("Path((points: [(2, 3), (2, 3)], speed: 1.0))");
+    site.update(5.0);
+    assert_eq!((site.pos.x, site.pos.y), (2.0, 3.0));
+}
+
+#[test]
+fn orbits_need_a_positive_period() {
+    let orbit = |period: f32| {
+        let motion = format!("Orbit((center: (5, 5), radius: 1, period: {period:?}))");
+        ron::from_str::<Motion>(&motion).unwrap().check()
+    };
+    assert!(orbit(10.0).is_ok());
+    assert!(orbit(0.0).is_err());
+    assert!(orbit(-1.0).is_err());
+}
@@ -0,0 +1,31 @@
+//! The spatial index finds every item near a point, whatever cell size it is asked for.
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use sales_ants::{math::Vec2, sim::spatial::SpatialIndex};
+
+const WORLD: Vec2 = Vec2 { x: 16.0, y: 10.0 };
+
+#[test]
+fn near_finds_every_item_in_range() {
+    let mut rng = ChaCha8Rng::seed_from_u64(0);
+    let positions: Vec<Vec2> = (0..500)
+        .map(|_| Vec2::new(rng.gen_range(0.0..WORLD.x), rng.gen_range(0.0..WORLD.y)))
+        .collect();
+    // A cell far smaller than the items are apart used to mean a grid of billions of cells
+    for cell in [1e-6, 0.05, 1.0, 20.0] {
+        let index = SpatialIndex::new(WORLD, cell, positions.iter().copied());
+        for &pos in &positions {
+            let radius = 0.5;
+            let near: Vec<usize> = index.near(pos, radius).collect();
+            for (item, &other) in positions.iter().enumerate() {
+                if (other - pos).sq_mag() <= radius * radius {
+                    assert!(
+                        near.contains(&item),
+                        "cell {cell}: missed {other:?} near {pos:?}"
+                    );
+                }
+            }
+        }
+    }
+}
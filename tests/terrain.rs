@@ -0,0 +1,37 @@
+//! Terrain only ever slows agents down or speeds them up.
+
+use sales_ants::load_scene;
+
+/// A scene with one region of terrain at `speed`.
+fn scene(speed: f32) -> String {
+    format!(
+        "World(
+            world_size: (16, 10),
+            terrain: [(shape: Rect((6, 10), (0, 10)), speed: {speed:?})],
+            sites: [
+                Site(pos: (4, 2), kind: 0, size: 0.2),
+                Site(pos: (12, 8), kind: 1, size: 0.2),
+            ],
+            site_kinds: [(0xff, 0x00, 0x00), (0x00, 0xff, 0x00)],
+            obstacles: [],
+        )"
+    )
+}
+
+#[test]
+fn passable_terrain_loads() {
+    load_scene(&scene(0.25));
+    load_scene(&scene(2.0));
+}
+
+#[test]
+#[should_panic(expected = "terrain speeds must be positive")]
+fn impassable_terrain_is_rejected() {
+    load_scene(&scene(0.0));
+}
+
+#[test]
+#[should_panic(expected = "terrain speeds must be positive")]
+fn backwards_terrain_is_rejected() {
+    load_scene(&scene(-1.0));
+}
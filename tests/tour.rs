@@ -0,0 +1,65 @@
+//! Agents follow an ordered tour the whole way round, even when it repeats a kind.
+
+use sales_ants::{
+    agent::{SiteState, State},
+    sim::tour::Tour,
+};
+
+/// A new agent's state for `tour` over `n_kinds` kinds.
+fn state(tour: &Tour, n_kinds: usize) -> State {
+    State {
+        sites: tour
+            .initial_targets(n_kinds)
+            .into_iter()
+            .map(|targeting| SiteState {
+                targeting,
+                ..Default::default()
+            })
+            .collect(),
+        target: None,
+        last_site: None,
+        tour_start: None,
+        tour_step: None,
+    }
+}
+
+/// Arrives at whichever kind is targeted next, starting at `first`. -> kinds visited, and the
+/// visits that completed a tour
+fn follow(tour: &Tour, n_kinds: usize, first: usize, visits: usize) -> (Vec<usize>, Vec<usize>) {
+    let mut state = state(tour, n_kinds);
+    let mut kind = first;
+    let (mut visited, mut completed) = (Vec::new(), Vec::new());
+    for visit in 0..visits {
+        visited.push(kind);
+        if tour.arrive(kind, &mut state) {
+            completed.push(visit);
+        }
+        let targets: Vec<usize> = (0..n_kinds).filter(|&k| state.sites[k].targeting).collect();
+        assert_eq!(targets.len(), 1, "after {visited:?} targeting {targets:?}");
+        kind = targets[0];
+    }
+    (visited, completed)
+}
+
+#[test]
+fn repeated_kinds_are_visited_in_order() {
+    let tour = Tour::Ordered(vec![0, 1, 0, 2]);
+    let (visited, completed) = follow(&tour, 3, 0, 9);
+    assert_eq!(visited, [0, 1, 0, 2, 0, 1, 0, 2, 0]);
+    assert_eq!(completed, [4, 8]);
+}
+
+#[test]
+fn tours_can_start_part_way_round() {
+    let tour = Tour::Ordered(vec![0, 1, 0, 2]);
+    let (visited, completed) = follow(&tour, 3, 2, 5);
+    assert_eq!(visited, [2, 0, 1, 0, 2]);
+    assert_eq!(completed, [4]);
+}
+
+#[test]
+fn orders_must_name_kinds_with_sites() {
+    assert!(Tour::Ordered(vec![0, 1, 0, 2]).check(3).is_ok());
+    assert!(Tour::Ordered(vec![0, 3]).check(3).is_err());
+    assert!(Tour::Ordered(vec![]).check(3).is_err());
+}